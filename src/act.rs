@@ -1,21 +1,137 @@
 use super::*;
+use crate::coord::Claim;
+use log::{debug, info, warn};
+use std::ffi::{OsStr, OsString};
 use std::fs::{remove_file, OpenOptions};
 use std::io::Write;
 
-pub fn act(game: &Game, args: &ActArgs) {
-    let action = ai::run(&game);
-    eprintln!("AI thinks we should move {:?}", action);
-    let action_str = action.to_string() + "\n";
-    let mut act_path = args.client_repo_path.clone();
+/// A player we control and the client repo its moves go in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Account {
+    pub player: String,
+    pub client_repo_path: OsString,
+}
+
+fn write_act_file(client_repo_path: &OsStr, direction: Direction) {
+    let action_str = direction.to_string() + "\n";
+    let mut act_path = client_repo_path.to_owned();
     act_path.push("/act");
-    eprintln!("Writing {:?} to {:?}", action_str, act_path);
+    debug!("Writing {:?} to {:?}", action_str, act_path);
     remove_file(&act_path).expect("failed to remove old file");
     let mut act_file = OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .open(&act_path)
         .expect("failed to open act file");
     act_file
         .write_all(action_str.as_bytes())
         .expect("failed to write to file");
 }
+
+/// A decision for a direction picked by something other than the built in AI
+fn decision_from(game: &Game, direction: Direction, source: String) -> ai::Decision {
+    ai::Decision {
+        direction,
+        target: None,
+        next: game.our_position().step(direction),
+        path: Vec::new(),
+        reasoning: vec![format!("{} chose {}", source, direction)],
+    }
+}
+
+/// Uses the external program if there is one, then the script, falling back to the built in AI if
+/// neither is set or they fail
+fn decide(game: &Game, config: &Config, teammates: &[Claim]) -> ai::Decision {
+    if let Some(external) = &config.external {
+        match external::decide(game, external) {
+            Ok(direction) => {
+                return decision_from(game, direction, format!("`{}`", external.command))
+            }
+            Err(e) => warn!("External strategy failed: {}", e),
+        }
+    }
+    if let Some(script) = &config.script {
+        match script::decide(game, script) {
+            Ok(direction) => {
+                return decision_from(game, direction, script.path.display().to_string())
+            }
+            Err(e) => warn!("Script failed: {}", e),
+        }
+    }
+    ai::run(game, &config.ai, teammates)
+}
+
+/// Decides on a move for each account in order and writes it to the account's client repo. Each
+/// account sees the ones before it as teammates, so they do not chase the same target or walk into
+/// each other. Accounts that are not on the map are skipped, so not every account gets a decision.
+pub fn act(
+    game: &mut Game,
+    accounts: &[Account],
+    config: &Config,
+    teammates: &[Claim],
+) -> Vec<(Account, ai::Decision)> {
+    let original_us = game.us.take();
+    let mut claims = teammates.to_vec();
+    let mut decisions = Vec::new();
+    for account in accounts {
+        if !game.map().players.contains_key(&account.player) {
+            warn!("{} is not on the map, not moving them", account.player);
+            continue;
+        }
+        game.us = Some(account.player.clone());
+        let decision = decide(game, config, &claims);
+        info!(
+            "AI thinks {} should move {:?}",
+            account.player, decision.direction
+        );
+        write_act_file(&account.client_repo_path, decision.direction);
+        claims.push(Claim::new(&account.player, &decision, &config.ai.zone));
+        decisions.push((account.clone(), decision));
+    }
+    game.us = original_us;
+    decisions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures;
+    use std::fs;
+
+    #[test]
+    fn accounts_split_targets_and_avoid_each_other() {
+        let map = fixtures::map(
+            &["............"; 6],
+            &[("a", 2, 2), ("b", 3, 1), ("enemy", 8, 2)],
+        );
+        let mut game = fixtures::game(
+            &[("a", Team::Red), ("b", Team::Red), ("enemy", Team::Blue)],
+            vec![map],
+        );
+        let root = std::env::temp_dir().join(format!("gitland-act-test-{}", std::process::id()));
+        let accounts: Vec<Account> = ["a", "b"]
+            .iter()
+            .map(|player| {
+                let dir = root.join(player);
+                fs::create_dir_all(&dir).unwrap();
+                fs::write(dir.join("act"), "idle\n").unwrap();
+                Account {
+                    player: player.to_string(),
+                    client_repo_path: dir.into_os_string(),
+                }
+            })
+            .collect();
+        let decisions = act(&mut game, &accounts, &Config::default(), &[]);
+        let written = fs::read_to_string(root.join("b").join("act")).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        let (a, b) = (&decisions[0].1, &decisions[1].1);
+        // Only the first account goes after the enemy
+        assert_eq!(a.target, Some(Position::new(5, 2)));
+        assert_ne!(b.target, Some(Position::new(5, 2)));
+        // Down would be b's best move if a was not about to step there
+        assert_eq!(a.next, Position::new(3, 2));
+        assert_ne!(b.next, a.next);
+        assert_eq!(written, format!("{}\n", b.direction));
+    }
+}
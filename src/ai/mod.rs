@@ -1,29 +1,53 @@
+mod search;
+
+use crate::config::{AiConfig, TieBreak};
+use crate::coord::Claim;
+use crate::danger::DangerMap;
+use crate::profile::{self, Behavior};
+use crate::zone::Zone;
 use crate::*;
+use log::{log, Level};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::cmp::Reverse;
+use std::collections::HashMap;
 
-const ZONE_SIZE: i32 = 5;
 const DEFEND_RANGE: i32 = 10;
+/// How sure we need to be a player is idle to stop defending against them
+const IDLE_CONFIDENCE: f32 = 0.8;
+/// Scores closer than this are considered tied
+const TIE_EPSILON: f32 = 0.0001;
+/// How close to a guard post counts as being on it
+const GUARD_SLACK: i32 = 2;
 
 fn count_good_squares_in_direction(
     map: &Map,
     starting: Position,
-    vector: Position,
+    direction: Direction,
     our_team: Team,
     falloff: f32,
-    stay_in_zone: bool,
+    stay_in: Option<&Zone>,
+    danger: Option<(&DangerMap, f32)>,
 ) -> f32 {
     let mut pos = starting;
     let mut score = 0.0;
     let mut value = 1.0;
-    if vector.x == 0 && vector.y == 0 {
+    if direction == Direction::Null {
         return 0.0;
     }
     loop {
-        pos = pos + vector;
-        if stay_in_zone && (pos.x >= ZONE_SIZE || pos.y >= ZONE_SIZE) {
+        pos = pos.step(direction);
+        if stay_in.is_some_and(|zone| !zone.contains(pos)) {
             break;
         }
         if let Some(square) = map.square(pos) {
             if square.occupied_by_player.is_none() {
+                // Painting a square that will be taken straight back is mostly wasted
+                let value = match danger {
+                    Some((danger, weight)) => value * (1.0 - weight * danger.risk(pos)),
+                    None => value,
+                };
                 if square.controlled_by == Team::Null {
                     score += value * 0.8;
                 } else if square.controlled_by != our_team {
@@ -42,111 +66,260 @@ fn count_good_squares_in_direction(
     score
 }
 
-fn find_target_square(game: &Game) -> Option<(Position, String)> {
+/// Teammates that are already heading for (or right next to) a target have it covered
+fn teammate_has_target(teammates: &[Claim], target: Position) -> bool {
+    teammates.iter().any(|claim| {
+        claim
+            .target
+            .is_some_and(|their_target| their_target.distance(target) <= 1)
+    })
+}
+
+/// The square in our zone closest to where enemies have spent the most time nearby, which is the
+/// corridor they use to approach it. None if we are already there.
+fn find_guard_post(game: &Game, zone: &Zone, teammates: &[Claim]) -> Option<Position> {
+    let our_pos = game.our_position();
+    let visits = game.visits();
+    let mut visits_by_post = HashMap::new();
+    for pos in game.map().positions() {
+        let count = visits.enemy_count(game.our_team(), pos);
+        if count == 0 || zone.contains(pos) {
+            continue;
+        }
+        if let Some(post) = zone.closest_to(pos) {
+            if post.distance(pos) <= DEFEND_RANGE {
+                *visits_by_post.entry(post).or_insert(0) += count;
+            }
+        }
+    }
+    let (post, _) = visits_by_post
+        .into_iter()
+        .filter(|(post, _)| !teammate_has_target(teammates, *post))
+        .max_by_key(|(post, count)| (*count, Reverse(post.distance(our_pos)), post.y, post.x))?;
+    if post.distance(our_pos) <= GUARD_SLACK {
+        None
+    } else {
+        Some(post)
+    }
+}
+
+fn find_target_square(
+    game: &Game,
+    config: &AiConfig,
+    teammates: &[Claim],
+) -> Option<(Position, String)> {
+    let zone = &config.zone;
     let starting = game.our_position();
-    if starting.x > ZONE_SIZE || starting.y > ZONE_SIZE {
-        let target = Position::new(
-            if starting.x > ZONE_SIZE {
-                ZONE_SIZE
-            } else {
-                starting.x
-            },
-            if starting.y > ZONE_SIZE {
-                ZONE_SIZE
-            } else {
-                starting.y
-            },
-        );
+    if !zone.contains(starting) {
+        let target = zone.closest_to(starting)?;
         Some((target, "enter zone".into()))
     } else {
         let mut threats = Vec::new();
         let map = game.map();
         let our_pos = game.our_position();
+        let idle: Vec<String> = profile::profile_players(game)
+            .into_iter()
+            .filter(|p| p.behavior == Behavior::Idle && p.confidence >= IDLE_CONFIDENCE)
+            .map(|p| p.name)
+            .collect();
         for (name, position) in &game.map().players {
             let their_team = game.players[name].team;
-            //let they_own_left_of_them =
-            //    map.controller_of(*position + Position::new(-1, 0)) == their_team;
-            //let they_own_top_of_them =
-            //   map.controller_of(*position + Position::new(0, -1)) == their_team;
             let dist_from_us = our_pos.distance(*position) - DEFEND_RANGE;
-            if their_team == game.our_team() || position.x == 0 || position.y == 0 {
-                // Hack until non-active players are kicked
+            if their_team == game.our_team() {
+                continue;
+            } else if game.players[name].inactive || idle.contains(name) {
+                // Players that are not moving can not take anything from us
                 continue;
-            } else if position.x > ZONE_SIZE && position.y <= ZONE_SIZE {
-                let dist = dist_from_us + position.x - ZONE_SIZE;
-                threats.push((dist, Position::new(ZONE_SIZE, position.y), name));
-            } else if position.x <= ZONE_SIZE && position.y > ZONE_SIZE {
-                let dist = dist_from_us + position.y - ZONE_SIZE;
-                threats.push((dist, Position::new(position.x, ZONE_SIZE), name));
-            } else if position.x > ZONE_SIZE && position.y > ZONE_SIZE {
-                let dist = dist_from_us + (position.x - ZONE_SIZE) + (position.y - ZONE_SIZE);
-                threats.push((dist, Position::new(ZONE_SIZE, ZONE_SIZE), name));
+            } else if !zone.contains(*position) {
+                if let Some(closest) = zone.closest_to(*position) {
+                    if teammate_has_target(teammates, closest) {
+                        continue;
+                    }
+                    let dist = dist_from_us + closest.distance(*position);
+                    threats.push((dist, closest, name));
+                }
             }
         }
         let biggest_threat = threats.iter().min_by_key(|threat| threat.0);
-        if let Some(threat) = biggest_threat {
-            if threat.0 < DEFEND_RANGE {
-                Some((
-                    threat.1,
-                    format!("defend against {} at {}", threat.2, map.players[threat.2]),
-                ))
-            } else {
-                None
-            }
-        } else {
-            None
+        match biggest_threat {
+            Some(threat) if threat.0 < DEFEND_RANGE => Some((
+                threat.1,
+                format!("defend against {} at {}", threat.2, map.players[threat.2]),
+            )),
+            _ if config.guard_corridors => find_guard_post(game, zone, teammates)
+                .map(|post| (post, "guard where enemies usually come from".to_owned())),
+            _ => None,
         }
     }
 }
 
-fn enemies_are_in_zone(game: &Game) -> bool {
+/// The closest square in our zone that another team controls and that borders our territory
+fn find_frontier_target(game: &Game, zone: &Zone) -> Option<Position> {
+    let pos = game.our_position();
+    game.map()
+        .frontier()
+        .bordering(game.our_team())
+        .map(|square| square.pos)
+        .filter(|target| zone.contains(*target))
+        .min_by_key(|target| target.distance(pos))
+}
+
+/// Inactive players sitting in our zone do not count
+pub fn enemies_are_in_zone(game: &Game, zone: &Zone) -> bool {
     for (name, position) in &game.map().players {
-        let their_team = game.players[name].team;
-        if their_team != game.our_team() && position.x <= ZONE_SIZE && position.y <= ZONE_SIZE {
+        let player = &game.players[name];
+        if player.team != game.our_team() && !player.inactive && zone.contains(*position) {
             return true;
         }
     }
     false
 }
 
-pub fn run(game: &Game) -> Direction {
+/// The direction we moved last tick, if we moved
+fn previous_direction(game: &Game) -> Option<Direction> {
+    let us = game.us.as_ref()?;
+    let previous = game.timeline.iter().rev().nth(1)?.players.get(us)?;
+    predict::direction_between(*previous, game.our_position())
+        .filter(|direction| *direction != Direction::Null)
+}
+
+/// Picks one of several equally good directions (which are in order of preference)
+fn break_tie(game: &Game, config: &AiConfig, tied: &[Direction]) -> Direction {
+    match config.tie_break {
+        TieBreak::Ordered => tied[0],
+        TieBreak::Random => {
+            // Mix in where we are so the same seed does not make the same choice every tick, but
+            // replaying a game with the same seed does
+            let pos = game.our_position();
+            let seed = config.seed.unwrap_or(0) ^ ((pos.x as u64) << 32) ^ (pos.y as u64);
+            *tied
+                .choose(&mut StdRng::seed_from_u64(seed))
+                .expect("no directions to choose from")
+        }
+        TieBreak::Straight => previous_direction(game)
+            .filter(|previous| tied.contains(previous))
+            .unwrap_or(tied[0]),
+    }
+}
+
+pub struct Decision {
+    pub direction: Direction,
+    pub target: Option<Position>,
+    /// Where we will be if the move works
+    pub next: Position,
+    /// Squares we plan to walk through to get to the target, starting with where we are
+    pub path: Vec<Position>,
+    /// Human readable notes on why the direction was chosen
+    pub reasoning: Vec<String>,
+}
+
+pub fn run(game: &Game, config: &AiConfig, teammates: &[Claim]) -> Decision {
+    let mut reasoning = Vec::new();
+    let mut note = |level: Level, line: String| {
+        log!(level, "{}", line);
+        reasoning.push(line);
+    };
     let pos = game.our_position();
-    eprintln!("We are at {}", pos);
+    note(Level::Info, format!("We are at {}", pos));
     let map = game.map();
     let team = game.our_team();
-    let leave_zone = enemies_are_in_zone(game);
-    let target = if leave_zone {
-        eprintln!("Enemies are in territory, might leave");
+    let leave_zone = enemies_are_in_zone(game, &config.zone);
+    let target: Option<Position> = if leave_zone {
+        note(
+            Level::Info,
+            "Enemies are in territory, might leave".to_owned(),
+        );
         None
     } else {
-        match find_target_square(game) {
+        match find_target_square(game, config, teammates) {
             Some((target, reason)) => {
-                eprintln!("Moving toward {} to {}", target, reason);
+                note(
+                    Level::Info,
+                    format!("Moving toward {} to {}", target, reason),
+                );
                 Some(target)
             }
             None => {
-                eprintln!("No enemies around");
-                None
+                note(Level::Info, "No enemies around".to_owned());
+                if config.target_frontier {
+                    let frontier_target = find_frontier_target(game, &config.zone);
+                    if let Some(target) = frontier_target {
+                        note(
+                            Level::Info,
+                            format!("Moving toward {} to paint the frontier", target),
+                        );
+                    }
+                    frontier_target
+                } else {
+                    None
+                }
             }
         }
     };
     // let target = find_enimy_square(map, pos, team);
-    let directions: Vec<(f32, Direction)> = vec![
-        (Position::new(0, 0), Direction::Null),
-        (Position::new(-1, 0), Direction::Left),
-        (Position::new(1, 0), Direction::Right),
-        (Position::new(0, -1), Direction::Up),
-        (Position::new(0, 1), Direction::Down),
+    let path = target.and_then(|target| path::find_path(map, pos, target, Some(team)));
+    match &path {
+        Some(path) => note(
+            Level::Info,
+            format!(
+                "Path to target is {} steps, starting {}",
+                path.squares.len() - 1,
+                path.first
+            ),
+        ),
+        None if target.is_some() => note(
+            Level::Info,
+            "No path to target, heading straight for it".to_owned(),
+        ),
+        None => (),
+    }
+    let search_gains = if config.search_depth > 0 {
+        note(
+            Level::Debug,
+            format!("Searching {} moves ahead", config.search_depth),
+        );
+        Some(search::territory_gain_by_first_move(game, config))
+    } else {
+        None
+    };
+    let danger = if config.danger_weight > 0.0 {
+        let danger = DangerMap::new(game, team, config.danger_horizon);
+        note(
+            Level::Debug,
+            format!("Recapture risk where we are is {:.2}", danger.risk(pos)),
+        );
+        Some(danger)
+    } else {
+        None
+    };
+    let directions: Vec<(f32, Direction)> = [
+        Direction::Null,
+        Direction::Left,
+        Direction::Right,
+        Direction::Up,
+        Direction::Down,
     ]
     .iter()
-    .map(|(vector, direction)| {
-        (
-            count_good_squares_in_direction(map, pos, *vector, team, 0.7, !leave_zone),
+    .map(|direction| match &search_gains {
+        Some(gains) => (gains[direction], *direction),
+        None => (
+            count_good_squares_in_direction(
+                map,
+                pos,
+                *direction,
+                team,
+                0.7,
+                Some(&config.zone).filter(|_| !leave_zone),
+                danger.as_ref().map(|danger| (danger, config.danger_weight)),
+            ),
             *direction,
-        )
+        ),
     })
     .map(|(score, direction)| {
-        if let Some(target) = target {
+        if let Some(path) = &path {
+            let score = score + if direction == path.first { 2.0 } else { 0.0 };
+            (score, direction)
+        } else if let Some(target) = target {
             let correct_way = match direction {
                 Direction::Null => target == pos,
                 Direction::Left => target.x < pos.x,
@@ -160,18 +333,103 @@ pub fn run(game: &Game) -> Direction {
             (score, direction)
         }
     })
+    .map(|(score, direction)| {
+        let next = pos.step(direction);
+        if direction != Direction::Null && teammates.iter().any(|claim| claim.next == Some(next)) {
+            // A teammate is moving there, so one of us would bounce off the other
+            (-1.0, direction)
+        } else {
+            (score, direction)
+        }
+    })
     .collect();
     for (score, direciton) in &directions {
-        eprintln!("{:?} score is {}", direciton, score);
+        note(Level::Debug, format!("{:?} score is {}", direciton, score));
     }
-    let (_score, best) = directions
+    let best_score = directions
         .iter()
-        .fold((0.0, Direction::Left), |best, current| {
-            if current.0 > best.0 {
-                *current
-            } else {
-                best
-            }
-        });
-    best
+        .map(|(score, _)| *score)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let tied: Vec<Direction> = directions
+        .iter()
+        .filter(|(score, _)| best_score - score < TIE_EPSILON)
+        .map(|(_, direction)| *direction)
+        .collect();
+    let best = break_tie(game, config, &tied);
+    if tied.len() > 1 {
+        note(
+            Level::Debug,
+            format!(
+                "Broke {:?} tie between {:?}: {}",
+                config.tie_break, tied, best
+            ),
+        );
+    }
+    Decision {
+        direction: best,
+        target,
+        next: pos.step(best),
+        path: path.map(|path| path.squares).unwrap_or_default(),
+        reasoning,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures;
+
+    fn game(enemy_inactive: bool) -> Game {
+        let map = fixtures::map(&["............"; 6], &[("us", 2, 2), ("them", 9, 2)]);
+        let mut game = fixtures::game(&[("us", Team::Red), ("them", Team::Blue)], vec![map]);
+        game.us = Some("us".to_owned());
+        game.players.get_mut("them").unwrap().inactive = enemy_inactive;
+        game
+    }
+
+    #[test]
+    fn defends_zone_against_enemies_outside_it() {
+        let config = AiConfig::default();
+        assert_eq!(
+            find_target_square(&game(false), &config, &[]),
+            Some((
+                Position::new(5, 2),
+                "defend against them at (9, 2)".to_owned()
+            ))
+        );
+        assert_eq!(find_target_square(&game(true), &config, &[]), None);
+    }
+
+    #[test]
+    fn tie_breaks() {
+        let tied = [Direction::Left, Direction::Up, Direction::Down];
+        let ordered = AiConfig::default();
+        let game = game(false);
+        assert_eq!(break_tie(&game, &ordered, &tied), Direction::Left);
+        let random = |seed| AiConfig {
+            tie_break: TieBreak::Random,
+            seed: Some(seed),
+            ..AiConfig::default()
+        };
+        let choice = break_tie(&game, &random(7), &tied);
+        assert!(tied.contains(&choice));
+        for _ in 0..5 {
+            assert_eq!(break_tie(&game, &random(7), &tied), choice);
+        }
+        let straight = AiConfig {
+            tie_break: TieBreak::Straight,
+            ..AiConfig::default()
+        };
+        // Without a previous move it falls back to ordered
+        assert_eq!(break_tie(&game, &straight, &tied), Direction::Left);
+        // Moved down last tick
+        let mut game = game;
+        let moved = fixtures::map(&["............"; 6], &[("us", 2, 3), ("them", 9, 2)]);
+        game.timeline.push(moved);
+        assert_eq!(break_tie(&game, &straight, &tied), Direction::Down);
+        assert_eq!(
+            break_tie(&game, &straight, &[Direction::Left, Direction::Up]),
+            Direction::Left
+        );
+    }
 }
@@ -0,0 +1,81 @@
+use crate::config::AiConfig;
+use crate::sim;
+use crate::*;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+/// Beam search over our next config.search_depth moves, assuming everyone else follows
+/// sim::predict_move. Returns how many squares our team gains in the best line starting with each
+/// first move.
+pub fn territory_gain_by_first_move(game: &Game, config: &AiConfig) -> HashMap<Direction, f32> {
+    let us = game.us.as_ref().expect("we do not have a player");
+    let team = game.our_team();
+    let map = game.map();
+    let current = sim::squares_controlled_by(map, team) as f32;
+    let advance = |map: &Map, direction: Direction| {
+        let mut moves = HashMap::new();
+        moves.insert(us.clone(), direction);
+        sim::step(map, &game.players, &moves)
+    };
+    Direction::ALL
+        .iter()
+        .map(|first| {
+            let mut beam = vec![advance(map, *first)];
+            for _ in 1..config.search_depth {
+                let mut candidates: Vec<(usize, Map)> = beam
+                    .iter()
+                    .flat_map(|map| {
+                        Direction::ALL
+                            .iter()
+                            .map(move |direction| advance(map, *direction))
+                    })
+                    .map(|map| (sim::squares_controlled_by(&map, team), map))
+                    .collect();
+                candidates.sort_by_key(|candidate| Reverse(candidate.0));
+                // Lines that leave us in the same spot with the same score are almost always
+                // equivalent, so only keep one of each to leave room in the beam for others
+                let mut seen = HashSet::new();
+                candidates
+                    .retain(|(score, map)| seen.insert((*score, map.players.get(us).copied())));
+                candidates.truncate(config.search_beam_width.max(1));
+                beam = candidates.into_iter().map(|(_, map)| map).collect();
+            }
+            let best = beam
+                .iter()
+                .map(|map| sim::squares_controlled_by(map, team))
+                .max()
+                .unwrap_or(0);
+            (*first, best as f32 - current)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures;
+
+    #[test]
+    fn looks_past_dead_ends() {
+        // Stepping left grabs a square at once but leaves only our own squares to walk back over,
+        // while stepping right onto our own square opens up the rest of the row
+        let map = fixtures::map(&[".rr..."], &[("us", 1, 0)]);
+        let mut game = fixtures::game(&[("us", Team::Red)], vec![map]);
+        game.us = Some("us".to_owned());
+        let best = |search_depth| {
+            let config = AiConfig {
+                search_depth,
+                ..AiConfig::default()
+            };
+            let gains = territory_gain_by_first_move(&game, &config);
+            let best = Direction::ALL
+                .iter()
+                .copied()
+                .max_by(|a, b| gains[a].partial_cmp(&gains[b]).unwrap())
+                .unwrap();
+            (best, gains[&best])
+        };
+        assert_eq!(best(1), (Direction::Left, 1.0));
+        assert_eq!(best(3), (Direction::Right, 2.0));
+    }
+}
@@ -1,27 +1,94 @@
 use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use std::ffi::OsString;
+use wmww_gitland_bot::act::Account;
+use wmww_gitland_bot::daemon::DaemonArgs;
+use wmww_gitland_bot::dataset::DatasetArgs;
+use wmww_gitland_bot::logging::{LogFilter, LogFormat};
+use wmww_gitland_bot::predict_eval::PredictEvalArgs;
+use wmww_gitland_bot::show::ShowArgs;
+use wmww_gitland_bot::snapshot::SnapshotArgs;
 
 #[derive(Debug, PartialEq)]
 pub struct ActArgs {
-    pub client_repo_path: OsString,
+    pub accounts: Vec<Account>,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum Command {
-    Show,
+    Show(ShowArgs),
+    Frontier,
+    Players,
     Act(ActArgs),
+    Daemon(DaemonArgs),
+    PredictEval(PredictEvalArgs),
+    Dataset(DatasetArgs),
+    Snapshot(SnapshotArgs),
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Arguments {
-    pub server_repo_path: OsString,
+    /// None if the game is loaded from a snapshot
+    pub server_repo_path: Option<OsString>,
+    pub snapshot_path: Option<OsString>,
+    /// The player to act as when there is only one
+    pub player: String,
+    pub config_path: Option<OsString>,
+    pub log_filter: LogFilter,
+    pub log_format: LogFormat,
     pub command: Command,
 }
 
+fn account_args<'a>() -> Vec<Arg<'a, 'a>> {
+    vec![
+        Arg::with_name("CLIENT_REPO")
+            .short("c")
+            .long("client-repo")
+            .env("GITLAND_CLIENT_REPO")
+            .value_name("DIRECTORY")
+            .help("Sets the directory to look for the client repo in")
+            .takes_value(true)
+            .required_unless("ACCOUNT"),
+        Arg::with_name("ACCOUNT")
+            .short("a")
+            .long("account")
+            .value_name("PLAYER=DIRECTORY")
+            .help(
+                "Adds a player to control and its client repo, can be given multiple times. \
+                 Overrides --client-repo.",
+            )
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1),
+    ]
+}
+
+fn parse_accounts(subcommand: &ArgMatches, player: &str) -> Vec<Account> {
+    match subcommand.values_of("ACCOUNT") {
+        Some(values) => values
+            .map(|value| {
+                let (player, client_repo_path) = value
+                    .split_once('=')
+                    .expect("account is not in the form PLAYER=DIRECTORY");
+                Account {
+                    player: player.to_owned(),
+                    client_repo_path: client_repo_path.into(),
+                }
+            })
+            .collect(),
+        None => vec![Account {
+            player: player.to_owned(),
+            client_repo_path: subcommand
+                .value_of_os("CLIENT_REPO")
+                .expect("failed to find client repo")
+                .into(),
+        }],
+    }
+}
+
 fn build_clap_app<'a>() -> App<'a, 'a> {
     App::new(crate_name!())
-        .version(&crate_version!()[..])
-        .author(crate_authors!("\n"))
+        .version(crate_version!())
+        .author(env!("CARGO_PKG_AUTHORS"))
         .about(crate_description!())
         .setting(AppSettings::SubcommandRequired)
         .arg(
@@ -31,55 +98,393 @@ fn build_clap_app<'a>() -> App<'a, 'a> {
                 .env("GITLAND_SERVER_REPO")
                 .value_name("DIRECTORY")
                 .help("Sets the directory to look for the server repo in")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("SNAPSHOT")
+                .long("snapshot")
+                .value_name("FILE")
+                .help("Loads the game from a snapshot file instead of the server repo (not for daemon)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("PLAYER")
+                .short("p")
+                .long("player")
+                .env("GITLAND_PLAYER")
+                .value_name("NAME")
+                .help("Sets the player we are")
+                .takes_value(true)
+                .default_value("wmww"),
+        )
+        .arg(
+            Arg::with_name("CONFIG")
+                .long("config")
+                .env("GITLAND_CONFIG")
+                .value_name("FILE")
+                .help("Sets the TOML config file to use")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("LOG_LEVEL")
+                .long("log-level")
+                .env("GITLAND_LOG_LEVEL")
+                .value_name("FILTER")
+                .help("Sets the log level, optionally per target (ex info,ai=debug)")
                 .takes_value(true)
-                .required(true),
+                .default_value("warn"),
+        )
+        .arg(
+            Arg::with_name("LOG_FORMAT")
+                .long("log-format")
+                .value_name("FORMAT")
+                .help("Sets how log lines are written")
+                .takes_value(true)
+                .possible_values(&["text", "json"])
+                .default_value("text"),
         )
         .subcommand(
             SubCommand::with_name("show")
                 .about("Display the map")
-                .version(&crate_version!()[..])
-                .author(crate_authors!("\n")),
+                .version(crate_version!())
+                .author(env!("CARGO_PKG_AUTHORS"))
+                .arg(
+                    Arg::with_name("OVERLAY")
+                        .long("overlay")
+                        .value_name("NAME")
+                        .help("Also draws the map with per-square data")
+                        .takes_value(true)
+                        .possible_values(&["danger", "visits"]),
+                )
+                .arg(
+                    Arg::with_name("CSV")
+                        .long("csv")
+                        .help("Prints only the overlay, as CSV")
+                        .requires("OVERLAY"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("frontier")
+                .about("Display where territory of different teams meets")
+                .version(crate_version!())
+                .author(env!("CARGO_PKG_AUTHORS")),
+        )
+        .subcommand(
+            SubCommand::with_name("players")
+                .about("List players and what they seem to be doing")
+                .version(crate_version!())
+                .author(env!("CARGO_PKG_AUTHORS")),
         )
         .subcommand(
             SubCommand::with_name("act")
                 .about("Make a move")
-                .version(&crate_version!()[..])
-                .author(crate_authors!("\n"))
+                .version(crate_version!())
+                .author(env!("CARGO_PKG_AUTHORS"))
+                .args(&account_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("daemon")
+                .about("Keep pulling the server and make a move each time it advances")
+                .version(crate_version!())
+                .author(env!("CARGO_PKG_AUTHORS"))
+                .args(&account_args())
+                .arg(
+                    Arg::with_name("PUSH_REMOTE")
+                        .long("push-remote")
+                        .value_name("REMOTE")
+                        .help("Sets the client repo remote to push moves to")
+                        .takes_value(true)
+                        .default_value("deploy"),
+                )
                 .arg(
-                    Arg::with_name("CLIENT_REPO")
-                        .short("c")
-                        .long("client-repo")
-                        .env("GITLAND_CLIENT_REPO")
-                        .value_name("DIRECTORY")
-                        .help("Sets the directory to look for the client repo in")
+                    Arg::with_name("POLL_INTERVAL")
+                        .long("poll-interval")
+                        .value_name("SECONDS")
+                        .help("Sets how long to sleep between server pulls")
                         .takes_value(true)
-                        .required(true),
+                        .default_value("20"),
+                )
+                .arg(
+                    Arg::with_name("STATUS_ADDR")
+                        .long("status-addr")
+                        .env("GITLAND_STATUS_ADDR")
+                        .value_name("ADDRESS")
+                        .help("Serves a JSON status page on the given loopback address (ex 127.0.0.1:8035)")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("METRICS_FILE")
+                        .long("metrics-file")
+                        .env("GITLAND_METRICS_FILE")
+                        .value_name("FILE")
+                        .help("Writes Prometheus metrics to the given .prom file after each tick")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("predict-eval")
+                .about("Train the enemy move predictor on older history and test it on newer")
+                .version(crate_version!())
+                .author(env!("CARGO_PKG_AUTHORS"))
+                .arg(
+                    Arg::with_name("HISTORY")
+                        .long("history")
+                        .value_name("COMMITS")
+                        .help("Sets how many server commits to load")
+                        .takes_value(true)
+                        .default_value("200"),
+                )
+                .arg(
+                    Arg::with_name("TRAIN_FRACTION")
+                        .long("train-fraction")
+                        .value_name("FRACTION")
+                        .help("Sets how much of the history (oldest first) to train on")
+                        .takes_value(true)
+                        .default_value("0.8"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("dataset")
+                .about("Export every move in server history with what the player saw and gained")
+                .version(crate_version!())
+                .author(env!("CARGO_PKG_AUTHORS"))
+                .arg(
+                    Arg::with_name("HISTORY")
+                        .long("history")
+                        .value_name("COMMITS")
+                        .help("Sets how many server commits to load")
+                        .takes_value(true)
+                        .default_value("200"),
+                )
+                .arg(
+                    Arg::with_name("RADIUS")
+                        .long("radius")
+                        .value_name("SQUARES")
+                        .help("Sets how far around the player the map window reaches")
+                        .takes_value(true)
+                        .default_value("3"),
+                )
+                .arg(
+                    Arg::with_name("OUTCOME_TICKS")
+                        .long("outcome-ticks")
+                        .value_name("TICKS")
+                        .help(
+                            "Sets how many ticks after each move territory change is measured over",
+                        )
+                        .takes_value(true)
+                        .default_value("5"),
+                )
+                .arg(
+                    Arg::with_name("FORMAT")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Sets the output format")
+                        .takes_value(true)
+                        .possible_values(&["ndjson", "csv"])
+                        .default_value("ndjson"),
+                )
+                .arg(
+                    Arg::with_name("OUTPUT")
+                        .short("o")
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Writes to the given file instead of standard output")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("snapshot")
+                .about("Save the game to a file, or check a saved one")
+                .version(crate_version!())
+                .author(env!("CARGO_PKG_AUTHORS"))
+                .setting(AppSettings::SubcommandRequired)
+                .subcommand(
+                    SubCommand::with_name("save")
+                        .about("Write the game to a file that --snapshot can load")
+                        .arg(
+                            Arg::with_name("FILE")
+                                .help("Sets the file to write")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("HISTORY")
+                                .long("history")
+                                .value_name("COMMITS")
+                                .help("Sets how many server commits to include")
+                                .takes_value(true)
+                                .default_value("12"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("load")
+                        .about("Read a snapshot file and describe what it holds")
+                        .arg(
+                            Arg::with_name("FILE")
+                                .help("Sets the file to read")
+                                .required(true),
+                        ),
                 ),
         )
 }
 
 fn parse_matches(matches: &ArgMatches) -> Arguments {
-    let server_repo_path = matches
-        .value_of_os("SERVER_REPO")
-        .expect("failed to find server repo in arguments")
-        .into();
+    let server_repo_path = matches.value_of_os("SERVER_REPO").map(Into::into);
+    let snapshot_path = matches.value_of_os("SNAPSHOT").map(Into::into);
+    let player = matches
+        .value_of("PLAYER")
+        .expect("failed to find player")
+        .to_owned();
+    let config_path = matches.value_of_os("CONFIG").map(Into::into);
+    let log_filter = matches
+        .value_of("LOG_LEVEL")
+        .expect("failed to find log level")
+        .parse()
+        .expect("invalid log level");
+    let log_format = matches
+        .value_of("LOG_FORMAT")
+        .expect("failed to find log format")
+        .parse()
+        .expect("invalid log format");
+    if snapshot_path.is_some() && matches.subcommand_name() == Some("daemon") {
+        panic!("the daemon follows the server repo, so it can not be run on a snapshot");
+    }
     let command = match matches.subcommand_name() {
-        Some("show") => Command::Show,
+        Some(name @ "show") => {
+            let subcommand = matches
+                .subcommand_matches(name)
+                .expect("did not find subcommand");
+            let overlay = subcommand
+                .value_of("OVERLAY")
+                .map(|overlay| overlay.parse().expect("invalid overlay"));
+            let csv = subcommand.is_present("CSV");
+            Command::Show(ShowArgs { overlay, csv })
+        }
+        Some("frontier") => Command::Frontier,
+        Some("players") => Command::Players,
         Some(name @ "act") => {
             let subcommand = matches
                 .subcommand_matches(name)
                 .expect("did not find subcommand");
-            let client_repo_path = subcommand
-                .value_of_os("CLIENT_REPO")
-                .expect("failed to find client repo")
-                .into();
-            Command::Act(ActArgs { client_repo_path })
+            let accounts = parse_accounts(subcommand, &player);
+            Command::Act(ActArgs { accounts })
+        }
+        Some(name @ "daemon") => {
+            let subcommand = matches
+                .subcommand_matches(name)
+                .expect("did not find subcommand");
+            let accounts = parse_accounts(subcommand, &player);
+            let push_remote = subcommand
+                .value_of("PUSH_REMOTE")
+                .expect("failed to find push remote")
+                .to_owned();
+            let poll_interval_secs = subcommand
+                .value_of("POLL_INTERVAL")
+                .expect("failed to find poll interval")
+                .parse()
+                .expect("poll interval is not a whole number of seconds");
+            let status_addr = subcommand.value_of("STATUS_ADDR").map(str::to_owned);
+            let metrics_path = subcommand.value_of_os("METRICS_FILE").map(Into::into);
+            Command::Daemon(DaemonArgs {
+                accounts,
+                push_remote,
+                poll_interval_secs,
+                status_addr,
+                metrics_path,
+            })
+        }
+        Some(name @ "predict-eval") => {
+            let subcommand = matches
+                .subcommand_matches(name)
+                .expect("did not find subcommand");
+            let history = subcommand
+                .value_of("HISTORY")
+                .expect("failed to find history")
+                .parse()
+                .expect("history is not a whole number");
+            let train_fraction = subcommand
+                .value_of("TRAIN_FRACTION")
+                .expect("failed to find train fraction")
+                .parse()
+                .expect("train fraction is not a number");
+            Command::PredictEval(PredictEvalArgs {
+                history,
+                train_fraction,
+            })
+        }
+        Some(name @ "dataset") => {
+            let subcommand = matches
+                .subcommand_matches(name)
+                .expect("did not find subcommand");
+            let history = subcommand
+                .value_of("HISTORY")
+                .expect("failed to find history")
+                .parse()
+                .expect("history is not a whole number");
+            let radius = subcommand
+                .value_of("RADIUS")
+                .expect("failed to find radius")
+                .parse()
+                .expect("radius is not a whole number");
+            let outcome_ticks = subcommand
+                .value_of("OUTCOME_TICKS")
+                .expect("failed to find outcome ticks")
+                .parse()
+                .expect("outcome ticks is not a whole number");
+            let format = subcommand
+                .value_of("FORMAT")
+                .expect("failed to find format")
+                .parse()
+                .expect("invalid dataset format");
+            let output = subcommand.value_of_os("OUTPUT").map(Into::into);
+            Command::Dataset(DatasetArgs {
+                history,
+                radius,
+                outcome_ticks,
+                format,
+                output,
+            })
+        }
+        Some(name @ "snapshot") => {
+            let subcommand = matches
+                .subcommand_matches(name)
+                .expect("did not find subcommand");
+            let file = |matches: &ArgMatches| {
+                matches
+                    .value_of_os("FILE")
+                    .expect("failed to find file")
+                    .into()
+            };
+            match subcommand.subcommand() {
+                ("save", Some(save)) => {
+                    let history = save
+                        .value_of("HISTORY")
+                        .expect("failed to find history")
+                        .parse()
+                        .expect("history is not a whole number");
+                    Command::Snapshot(SnapshotArgs::Save {
+                        path: file(save),
+                        history,
+                    })
+                }
+                ("load", Some(load)) => Command::Snapshot(SnapshotArgs::Load { path: file(load) }),
+                (cmd, _) => panic!("unknown snapshot subcommand: {}", cmd),
+            }
         }
         Some(cmd) => panic!("unknown subcommand: {}", cmd),
         None => panic!("no subcommand"),
     };
+    // Checking a snapshot only reads the file it is given
+    let loads_game = !matches!(command, Command::Snapshot(SnapshotArgs::Load { .. }));
+    if loads_game && server_repo_path.is_none() && snapshot_path.is_none() {
+        panic!("--server-repo or --snapshot is required to load the game");
+    }
     Arguments {
         server_repo_path,
+        snapshot_path,
+        player,
+        config_path,
+        log_filter,
+        log_format,
         command,
     }
 }
@@ -94,6 +499,11 @@ pub fn parse_arguments() -> Arguments {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
+    use wmww_gitland_bot::show::Overlay;
+
+    /// Held by tests that set environment variables or depend on them not being set
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
 
     fn try_to_parse(args: &[&str]) {
         let app = build_clap_app();
@@ -118,12 +528,193 @@ mod tests {
         assert_parses_to(
             &["--server-repo", "foobar", "show"],
             Arguments {
-                server_repo_path: "foobar".into(),
-                command: Command::Show,
+                server_repo_path: Some("foobar".into()),
+                snapshot_path: None,
+                player: "wmww".to_owned(),
+                config_path: None,
+                log_filter: "warn".parse().unwrap(),
+                log_format: LogFormat::Text,
+                command: Command::Show(ShowArgs {
+                    overlay: None,
+                    csv: false,
+                }),
+            },
+        );
+    }
+
+    #[test]
+    fn show_with_overlay() {
+        assert_parses_to(
+            &[
+                "--server-repo",
+                "foobar",
+                "show",
+                "--overlay",
+                "visits",
+                "--csv",
+            ],
+            Arguments {
+                server_repo_path: Some("foobar".into()),
+                snapshot_path: None,
+                player: "wmww".to_owned(),
+                config_path: None,
+                log_filter: "warn".parse().unwrap(),
+                log_format: LogFormat::Text,
+                command: Command::Show(ShowArgs {
+                    overlay: Some(Overlay::Visits),
+                    csv: true,
+                }),
+            },
+        );
+    }
+
+    #[test]
+    fn daemon() {
+        assert_parses_to(
+            &[
+                "--server-repo",
+                "foobar",
+                "--config",
+                "bot.toml",
+                "--log-level",
+                "warn,ai=debug",
+                "--log-format",
+                "json",
+                "daemon",
+                "--client-repo",
+                "bazbuz",
+                "--poll-interval",
+                "5",
+                "--status-addr",
+                "127.0.0.1:8035",
+            ],
+            Arguments {
+                server_repo_path: Some("foobar".into()),
+                snapshot_path: None,
+                player: "wmww".to_owned(),
+                config_path: Some("bot.toml".into()),
+                log_filter: "warn,ai=debug".parse().unwrap(),
+                log_format: LogFormat::Json,
+                command: Command::Daemon(DaemonArgs {
+                    accounts: vec![Account {
+                        player: "wmww".to_owned(),
+                        client_repo_path: "bazbuz".into(),
+                    }],
+                    push_remote: "deploy".to_owned(),
+                    poll_interval_secs: 5,
+                    status_addr: Some("127.0.0.1:8035".to_owned()),
+                    metrics_path: None,
+                }),
             },
         );
     }
 
+    #[test]
+    fn act_with_multiple_accounts() {
+        assert_parses_to(
+            &[
+                "--server-repo",
+                "foobar",
+                "--player",
+                "ignored",
+                "act",
+                "--account",
+                "alice=../alice-client",
+                "-a",
+                "bob=../bob-client",
+            ],
+            Arguments {
+                server_repo_path: Some("foobar".into()),
+                snapshot_path: None,
+                player: "ignored".to_owned(),
+                config_path: None,
+                log_filter: "warn".parse().unwrap(),
+                log_format: LogFormat::Text,
+                command: Command::Act(ActArgs {
+                    accounts: vec![
+                        Account {
+                            player: "alice".to_owned(),
+                            client_repo_path: "../alice-client".into(),
+                        },
+                        Account {
+                            player: "bob".to_owned(),
+                            client_repo_path: "../bob-client".into(),
+                        },
+                    ],
+                }),
+            },
+        );
+    }
+
+    #[test]
+    fn snapshots() {
+        assert_parses_to(
+            &["snapshot", "load", "bug.json"],
+            Arguments {
+                server_repo_path: None,
+                snapshot_path: None,
+                player: "wmww".to_owned(),
+                config_path: None,
+                log_filter: "warn".parse().unwrap(),
+                log_format: LogFormat::Text,
+                command: Command::Snapshot(SnapshotArgs::Load {
+                    path: "bug.json".into(),
+                }),
+            },
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "can not be run on a snapshot")]
+    fn daemon_does_not_take_a_snapshot() {
+        let app = build_clap_app();
+        let args = [
+            "./self",
+            "-s",
+            "foobar",
+            "--snapshot",
+            "bug.json",
+            "daemon",
+            "-c",
+            "bazbuz",
+        ];
+        parse_matches(&app.get_matches_from_safe(args).unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "--server-repo or --snapshot is required")]
+    fn server_repo_or_snapshot_is_needed() {
+        let app = build_clap_app();
+        parse_matches(&app.get_matches_from_safe(["./self", "show"]).unwrap());
+    }
+
+    #[test]
+    fn accounts_override_client_repo_from_env() {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        std::env::set_var("GITLAND_CLIENT_REPO", "from-env");
+        let app = build_clap_app();
+        let args = ["./self", "-s", "foobar", "act", "--account", "a=a-client"];
+        let matches = app.get_matches_from_safe(args);
+        std::env::remove_var("GITLAND_CLIENT_REPO");
+        let command = parse_matches(&matches.expect("failed to get matches")).command;
+        assert_eq!(
+            command,
+            Command::Act(ActArgs {
+                accounts: vec![Account {
+                    player: "a".to_owned(),
+                    client_repo_path: "a-client".into(),
+                }],
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "failed to get matches")]
+    fn act_needs_a_client_repo() {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        try_to_parse(&["--server-repo", "foobar", "act"]);
+    }
+
     #[test]
     #[should_panic(expected = "failed to get matches")]
     fn invalid_subcommand() {
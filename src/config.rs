@@ -0,0 +1,207 @@
+use crate::coord::CoordinationConfig;
+use crate::external::ExternalConfig;
+use crate::script::ScriptConfig;
+use crate::zone::Zone;
+use log::{info, warn};
+use serde::Deserialize;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Shell commands to run when something happens. Each gets a JSON payload on stdin.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// The bot panicked or failed to load the game
+    pub on_failure: Option<String>,
+    pub on_push_failure: Option<String>,
+    /// Our player was on the map last tick but is not now
+    pub on_player_missing: Option<String>,
+    /// An enemy entered our zone when none were in it last tick
+    pub on_enemies_in_zone: Option<String>,
+    /// Hooks still running after this long are killed
+    pub timeout_secs: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            on_failure: None,
+            on_push_failure: None,
+            on_player_missing: None,
+            on_enemies_in_zone: None,
+            timeout_secs: 30,
+        }
+    }
+}
+
+/// How to pick between moves that scored the same
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TieBreak {
+    /// The first of idle, left, right, up and down
+    Ordered,
+    /// Any of them, using AiConfig::seed
+    Random,
+    /// Keep going the way we went last tick if we can, otherwise ordered
+    Straight,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AiConfig {
+    /// How many moves ahead to search, or 0 to only look at the next move
+    pub search_depth: usize,
+    /// How many lines to keep at each step of the search
+    pub search_beam_width: usize,
+    /// When there is nothing to defend against, head for the nearest enemy square bordering ours
+    pub target_frontier: bool,
+    /// The area we defend and paint
+    pub zone: Zone,
+    /// When there is nothing to defend against, wait where enemies usually approach our zone from
+    pub guard_corridors: bool,
+    /// How much to discount squares likely to be recaptured, from 0 (ignore) to 1
+    pub danger_weight: f32,
+    /// How many ticks ahead recapture risk is estimated for
+    pub danger_horizon: u32,
+    pub tie_break: TieBreak,
+    /// Seed for random tie-breaking. Picked from the clock (and logged) if not set.
+    pub seed: Option<u64>,
+}
+
+impl Default for AiConfig {
+    fn default() -> Self {
+        Self {
+            search_depth: 0,
+            search_beam_width: 8,
+            target_frontier: false,
+            guard_corridors: false,
+            zone: Zone::default(),
+            danger_weight: 0.0,
+            danger_horizon: 3,
+            tie_break: TieBreak::Ordered,
+            seed: None,
+        }
+    }
+}
+
+impl AiConfig {
+    /// Picks a seed if random tie-breaking needs one, and logs it so runs can be reproduced
+    pub fn init_seed(&mut self) {
+        if self.tie_break != TieBreak::Random {
+            return;
+        }
+        let seed = match self.seed {
+            Some(seed) => {
+                info!("Tie-break seed is {}", seed);
+                seed
+            }
+            None => {
+                let seed = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .expect("clock is before 1970")
+                    .as_nanos() as u64;
+                // A warning so it is logged by default, since the run can not be reproduced
+                // without it
+                warn!("Picked tie-break seed {} (set ai.seed to reproduce)", seed);
+                seed
+            }
+        };
+        self.seed = Some(seed);
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub hooks: HooksConfig,
+    pub ai: AiConfig,
+    /// Sharing targets with our other bots, off if not set
+    pub coordination: Option<CoordinationConfig>,
+    /// A program that picks our moves instead of the built in AI, off if not set
+    pub external: Option<ExternalConfig>,
+    /// A Rhai script that picks our moves instead of the built in AI, off if not set
+    pub script: Option<ScriptConfig>,
+}
+
+impl Config {
+    pub fn load(path: &OsStr) -> Result<Self, Box<dyn Error>> {
+        info!("Loading config from {}", path.to_string_lossy());
+        let text = fs::read_to_string(path)?;
+        let config: Self = toml::from_str(&text)?;
+        config
+            .ai
+            .zone
+            .validate()
+            .map_err(|e| format!("invalid [ai] zone: {}", e))?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_config_is_default() {
+        let config: Config = toml::from_str("").expect("failed to parse");
+        assert!(config.hooks.on_failure.is_none());
+    }
+
+    #[test]
+    fn hooks() {
+        let config: Config = toml::from_str(
+            r#"
+            [hooks]
+            on_failure = "notify failed"
+            on_enemies_in_zone = "notify enemies"
+            "#,
+        )
+        .expect("failed to parse");
+        assert_eq!(config.hooks.on_failure.as_deref(), Some("notify failed"));
+        assert_eq!(
+            config.hooks.on_enemies_in_zone.as_deref(),
+            Some("notify enemies")
+        );
+        assert!(config.hooks.on_push_failure.is_none());
+    }
+
+    #[test]
+    fn partial_ai_config_keeps_defaults() {
+        let config: Config = toml::from_str("[ai]\nsearch_depth = 3").expect("failed to parse");
+        assert_eq!(config.ai.search_depth, 3);
+        assert_eq!(config.ai.search_beam_width, 8);
+    }
+
+    #[test]
+    fn zone() {
+        let config: Config = toml::from_str(
+            r#"
+            [ai]
+            zone = [
+                { x = 0, y = 0, width = 4, height = 4 },
+                { x = 20, y = 20, width = 2, height = 3 },
+            ]
+            "#,
+        )
+        .expect("failed to parse");
+        assert_eq!(config.ai.zone.rects.len(), 2);
+        assert_eq!(config.ai.zone.rects[1].height, 3);
+        assert_eq!(Config::default().ai.zone, Zone::default());
+    }
+
+    #[test]
+    fn tie_break() {
+        let config: Config =
+            toml::from_str("[ai]\ntie_break = \"random\"\nseed = 7").expect("failed to parse");
+        assert_eq!(config.ai.tie_break, TieBreak::Random);
+        assert_eq!(config.ai.seed, Some(7));
+        assert_eq!(Config::default().ai.tie_break, TieBreak::Ordered);
+    }
+
+    #[test]
+    fn unknown_hook_is_rejected() {
+        assert!(toml::from_str::<Config>("[hooks]\non_anything = \"x\"").is_err());
+    }
+}
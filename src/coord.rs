@@ -0,0 +1,239 @@
+//! Sharing what each of our bots is doing through a git repo they all push to, so they can split
+//! up the work
+
+use crate::git::{run_git, run_git_checked};
+use crate::status::unix_time;
+use crate::zone::Zone;
+use crate::*;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+
+const CLAIMS_DIR: &str = "claims";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CoordinationConfig {
+    /// A local clone of the shared repo
+    pub repo: PathBuf,
+    #[serde(default = "default_remote")]
+    pub remote: String,
+    /// Claims that have not been updated in this long are ignored
+    #[serde(default = "default_stale_after_secs")]
+    pub stale_after_secs: u64,
+}
+
+fn default_remote() -> String {
+    "origin".to_owned()
+}
+
+fn default_stale_after_secs() -> u64 {
+    120
+}
+
+/// What one bot is currently doing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claim {
+    pub player: String,
+    pub target: Option<Position>,
+    /// Squares we plan to walk through to get to the target
+    pub path: Vec<Position>,
+    /// The square we are moving onto this tick
+    #[serde(default)]
+    pub next: Option<Position>,
+    pub zone: Zone,
+    /// Seconds since the epoch
+    pub time: u64,
+}
+
+pub struct Coordinator {
+    config: CoordinationConfig,
+}
+
+impl Coordinator {
+    pub fn new(config: CoordinationConfig) -> Self {
+        Self { config }
+    }
+
+    fn repo_path(&self) -> OsString {
+        self.config.repo.clone().into_os_string()
+    }
+
+    /// Pulls the shared repo and returns fresh claims from everyone except the given players
+    pub fn teammate_claims(&self, us: &[String]) -> Result<Vec<Claim>, Box<dyn Error>> {
+        debug!("Pulling coordination repo");
+        run_git_checked(
+            &self.repo_path(),
+            &["pull", "--quiet", "--rebase", &self.config.remote, "master"],
+        )?;
+        let dir = self.config.repo.join(CLAIMS_DIR);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let now = unix_time();
+        let mut claims = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let claim: Claim = match fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| serde_json::from_str(&text).map_err(|e| e.to_string()))
+            {
+                Ok(claim) => claim,
+                Err(e) => {
+                    warn!("Ignoring claim {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            if !us.contains(&claim.player)
+                && now.saturating_sub(claim.time) <= self.config.stale_after_secs
+            {
+                claims.push(claim);
+            }
+        }
+        claims.sort_by(|a, b| a.player.cmp(&b.player));
+        Ok(claims)
+    }
+
+    /// Commits our claim and pushes it
+    pub fn publish(&self, claim: &Claim) -> Result<(), Box<dyn Error>> {
+        let dir = self.config.repo.join(CLAIMS_DIR);
+        fs::create_dir_all(&dir)?;
+        let file_name = format!("{}.json", claim.player);
+        fs::write(
+            dir.join(&file_name),
+            serde_json::to_string_pretty(claim)? + "\n",
+        )?;
+        let repo = self.repo_path();
+        let relative_path = format!("{}/{}", CLAIMS_DIR, file_name);
+        run_git_checked(&repo, &["add", &relative_path])?;
+        if run_git(&repo, &["diff", "--cached", "--quiet"])?.success() {
+            return Ok(());
+        }
+        debug!("Publishing claim for {}", claim.player);
+        run_git_checked(
+            &repo,
+            &[
+                "commit",
+                "--quiet",
+                "-m",
+                &format!("Update {} claim", claim.player),
+            ],
+        )?;
+        run_git_checked(
+            &repo,
+            &["push", "--quiet", &self.config.remote, "HEAD:master"],
+        )
+    }
+}
+
+impl Claim {
+    pub fn new(player: &str, decision: &ai::Decision, zone: &Zone) -> Self {
+        Self {
+            player: player.to_owned(),
+            target: decision.target,
+            path: decision.path.clone(),
+            next: Some(decision.next),
+            zone: zone.clone(),
+            time: unix_time(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .env("GIT_AUTHOR_NAME", "test")
+            .env("GIT_AUTHOR_EMAIL", "test@example.com")
+            .env("GIT_COMMITTER_NAME", "test")
+            .env("GIT_COMMITTER_EMAIL", "test@example.com")
+            .status()
+            .expect("failed to run git");
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    fn configure_identity(dir: &Path) {
+        git(dir, &["config", "user.name", "test"]);
+        git(dir, &["config", "user.email", "test@example.com"]);
+    }
+
+    #[test]
+    fn claims_are_shared_through_a_bare_repo() {
+        let root = std::env::temp_dir().join(format!("gitland-coord-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let bare = root.join("shared.git");
+        git(
+            &root,
+            &["init", "--quiet", "--bare", "-b", "master", "shared.git"],
+        );
+        let mut clones = Vec::new();
+        for name in &["a", "b"] {
+            git(&root, &["clone", "--quiet", bare.to_str().unwrap(), name]);
+            let clone = root.join(name);
+            configure_identity(&clone);
+            clones.push(clone);
+        }
+        git(
+            &clones[0],
+            &["commit", "--quiet", "--allow-empty", "-m", "init"],
+        );
+        git(&clones[0], &["push", "--quiet", "origin", "HEAD:master"]);
+        let coordinators: Vec<Coordinator> = clones
+            .iter()
+            .map(|clone| {
+                Coordinator::new(CoordinationConfig {
+                    repo: clone.clone(),
+                    remote: default_remote(),
+                    stale_after_secs: default_stale_after_secs(),
+                })
+            })
+            .collect();
+        let claim = Claim {
+            player: "alice".to_owned(),
+            target: Some(Position::new(3, 4)),
+            path: vec![Position::new(3, 3), Position::new(3, 4)],
+            next: Some(Position::new(3, 4)),
+            zone: Zone::default(),
+            time: unix_time(),
+        };
+        assert!(coordinators[0]
+            .teammate_claims(&["alice".to_owned()])
+            .unwrap()
+            .is_empty());
+        coordinators[0].publish(&claim).unwrap();
+        assert_eq!(
+            coordinators[1]
+                .teammate_claims(&["bob".to_owned()])
+                .unwrap(),
+            vec![claim.clone()]
+        );
+        // We never see our own claim
+        assert!(coordinators[0]
+            .teammate_claims(&["alice".to_owned()])
+            .unwrap()
+            .is_empty());
+        let stale = Claim {
+            player: "carol".to_owned(),
+            time: 0,
+            ..claim.clone()
+        };
+        coordinators[1].publish(&stale).unwrap();
+        assert_eq!(
+            coordinators[0]
+                .teammate_claims(&["alice".to_owned()])
+                .unwrap(),
+            Vec::new()
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
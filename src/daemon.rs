@@ -0,0 +1,303 @@
+use crate::coord::{Claim, Coordinator};
+use crate::git::ClientRepo;
+use crate::hooks::{Event, Hooks};
+use crate::metrics::TickMetrics;
+use crate::status::{self, SharedStatus};
+use crate::*;
+use git2::Oid;
+use log::{debug, error, info, warn};
+use serde_json::json;
+use std::ffi::OsString;
+use std::path::Path;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+const ACTION_VERBS: &[&str] = &["Move", "Turn", "Walk", "Run", "Slide", "Vear", "Skidaddle"];
+
+#[derive(Debug, PartialEq)]
+pub struct DaemonArgs {
+    pub accounts: Vec<Account>,
+    pub push_remote: String,
+    pub poll_interval_secs: u64,
+    pub status_addr: Option<String>,
+    pub metrics_path: Option<OsString>,
+}
+
+struct AccountState {
+    account: Account,
+    client_repo: ClientRepo,
+    /// If we have committed a move that has not been pushed yet
+    push_pending: bool,
+    /// If we were on the map last time the game was loaded
+    was_on_map: bool,
+}
+
+struct Daemon<'a> {
+    server_repo: &'a ServerRepo,
+    accounts: Vec<AccountState>,
+    args: &'a DaemonArgs,
+    config: &'a Config,
+    hooks: &'a Hooks,
+    coordinator: Option<Coordinator>,
+    status: SharedStatus,
+    /// The server commit we last made a decision for
+    last_oid: Option<Oid>,
+    /// When we first saw last_oid
+    last_advance: Option<Instant>,
+    /// How long the server spent on the previous tick (time between master advancing)
+    last_tick_duration: Option<Duration>,
+    ticks: usize,
+    /// If enemies were in our zone last time the game was loaded
+    enemies_were_in_zone: bool,
+    /// If the last attempt to load the game failed
+    load_failing: bool,
+}
+
+impl<'a> Daemon<'a> {
+    fn new(
+        server_repo: &'a ServerRepo,
+        args: &'a DaemonArgs,
+        config: &'a Config,
+        hooks: &'a Hooks,
+    ) -> Self {
+        let accounts = args
+            .accounts
+            .iter()
+            .map(|account| AccountState {
+                account: account.clone(),
+                client_repo: ClientRepo::new(&account.client_repo_path),
+                push_pending: false,
+                was_on_map: true,
+            })
+            .collect();
+        Self {
+            server_repo,
+            accounts,
+            args,
+            config,
+            hooks,
+            coordinator: config.coordination.clone().map(Coordinator::new),
+            status: SharedStatus::default(),
+            last_oid: None,
+            last_advance: None,
+            last_tick_duration: None,
+            ticks: 0,
+            enemies_were_in_zone: false,
+            load_failing: false,
+        }
+    }
+
+    fn iteration(&mut self) -> Result<(), Box<dyn Error>> {
+        self.server_repo.pull()?;
+        let oid = self.server_repo.master_oid()?;
+        if self.last_oid == Some(oid) {
+            if self.accounts.iter().any(|state| state.push_pending) {
+                info!("Server is still at {}, retrying push", oid);
+                self.push_pending()?;
+            } else {
+                debug!("Server is still at {}, nothing to do", oid);
+            }
+            return Ok(());
+        }
+        let now = Instant::now();
+        if let Some(last_advance) = self.last_advance {
+            let duration = now - last_advance;
+            info!("Server tick took {:.1}s", duration.as_secs_f32());
+            self.last_tick_duration = Some(duration);
+        }
+        self.last_advance = Some(now);
+        info!("Server advanced to {}", oid);
+        let load_start = Instant::now();
+        let mut game = match load_game(self.server_repo, HISTORY_LIMIT) {
+            Ok(game) => game,
+            Err(e) => {
+                // Loading is retried every poll, but the hook only needs to hear about it once
+                if !self.load_failing {
+                    self.hooks.fire(
+                        Event::LoadFailure,
+                        &e.to_string(),
+                        json!({ "server_oid": oid.to_string() }),
+                    );
+                }
+                self.load_failing = true;
+                return Err(e);
+            }
+        };
+        self.load_failing = false;
+        let load_time = load_start.elapsed();
+        self.status
+            .lock()
+            .expect("status lock poisoned")
+            .update_game(&game, &self.args.accounts);
+        let on_map = self.check_game_events(&mut game);
+        if on_map.is_empty() {
+            self.last_oid = Some(oid);
+            return Err("none of our players are on the map".into());
+        }
+        // Coordination is git network I/O, so it is kept out of the decision time
+        let our_players: Vec<String> = self
+            .accounts
+            .iter()
+            .map(|state| state.account.player.clone())
+            .collect();
+        let teammates = match &self.coordinator {
+            Some(coordinator) => coordinator
+                .teammate_claims(&our_players)
+                .unwrap_or_else(|e| {
+                    self.report_error(format!("Failed to read teammate claims: {}", e));
+                    Vec::new()
+                }),
+            None => Vec::new(),
+        };
+        let decision_start = Instant::now();
+        let decisions = act(&mut game, &on_map, self.config, &teammates);
+        let decision_time = decision_start.elapsed();
+        if let Some(coordinator) = &self.coordinator {
+            for (account, decision) in &decisions {
+                let claim = Claim::new(&account.player, decision, &self.config.ai.zone);
+                if let Err(e) = coordinator.publish(&claim) {
+                    self.report_error(format!("Failed to publish claim: {}", e));
+                }
+            }
+        }
+        {
+            let mut status = self.status.lock().expect("status lock poisoned");
+            for (account, decision) in &decisions {
+                status.update_decision(&account.player, decision);
+            }
+            status.last_server_oid = Some(oid.to_string());
+        }
+        // Even if pushing fails below, we do not want to re-decide on the same server state
+        self.last_oid = Some(oid);
+        self.ticks += 1;
+        let mut any_move_changed = false;
+        for (account, decision) in &decisions {
+            let state = self
+                .accounts
+                .iter_mut()
+                .find(|state| state.account == *account)
+                .expect("decided for an unknown account");
+            if state.client_repo.act_changed()? {
+                any_move_changed = true;
+                let verb = ACTION_VERBS[self.ticks % ACTION_VERBS.len()];
+                info!("{} {}ing {}", account.player, verb, decision.direction);
+                state
+                    .client_repo
+                    .commit_act(&format!("{} {}", verb, decision.direction))?;
+                state.push_pending = true;
+            } else {
+                info!(
+                    "No change, {} still going {}",
+                    account.player, decision.direction
+                );
+            }
+        }
+        // Metrics are written after pushing so a problem with them can not hold up the move
+        let pushed = self.push_pending();
+        if let Some(path) = &self.args.metrics_path {
+            game.us = Some(on_map[0].player.clone());
+            let metrics = self
+                .server_repo
+                .commit_time(oid)
+                .map(|commit_time| TickMetrics {
+                    move_changed: any_move_changed,
+                    decision_time,
+                    load_time,
+                    tick_duration: self.last_tick_duration,
+                    last_server_commit_time: commit_time,
+                    ..TickMetrics::new(&game)
+                });
+            if let Err(e) = metrics.and_then(|metrics| metrics.write_textfile(Path::new(path))) {
+                self.report_error(format!("Failed to write metrics: {}", e));
+            }
+        }
+        pushed
+    }
+
+    /// For problems that should not stop the rest of the iteration
+    fn report_error(&self, message: String) {
+        warn!("{}", message);
+        self.status
+            .lock()
+            .expect("status lock poisoned")
+            .report_error(message);
+    }
+
+    /// Fires hooks for anything that changed since the last game, in the background so they can
+    /// not delay our move. Returns the accounts that are on the map.
+    fn check_game_events(&mut self, game: &mut Game) -> Vec<Account> {
+        let mut on_map = Vec::new();
+        for state in &mut self.accounts {
+            let player = &state.account.player;
+            let is_on_map = game.map().players.contains_key(player);
+            if !is_on_map && state.was_on_map {
+                self.hooks.fire_in_background(
+                    Event::PlayerMissing,
+                    format!("{} is not on the map", player),
+                    json!({ "player": player }),
+                );
+            }
+            state.was_on_map = is_on_map;
+            if is_on_map {
+                on_map.push(state.account.clone());
+            }
+        }
+        if let Some(first) = on_map.first() {
+            // All our players are on the same team and defend the same zone
+            game.us = Some(first.player.clone());
+            let enemies_in_zone = ai::enemies_are_in_zone(game, &self.config.ai.zone);
+            if enemies_in_zone && !self.enemies_were_in_zone {
+                self.hooks.fire_in_background(
+                    Event::EnemiesInZone,
+                    "enemies entered our zone".to_owned(),
+                    json!({ "our_position": game.our_position() }),
+                );
+            }
+            self.enemies_were_in_zone = enemies_in_zone;
+        }
+        on_map
+    }
+
+    /// Pushes every account with a committed but unpushed move
+    fn push_pending(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut result = Ok(());
+        for state in self.accounts.iter_mut().filter(|state| state.push_pending) {
+            if let Err(e) = state.client_repo.push(&self.args.push_remote) {
+                self.hooks.fire(
+                    Event::PushFailure,
+                    &e.to_string(),
+                    json!({ "player": state.account.player, "remote": self.args.push_remote }),
+                );
+                result = Err(e);
+                continue;
+            }
+            state.push_pending = false;
+            self.status
+                .lock()
+                .expect("status lock poisoned")
+                .last_push_time = Some(status::unix_time());
+        }
+        result
+    }
+}
+
+/// Runs forever, only acting when the server's master branch moves. Errors in a single iteration
+/// are reported and then retried on the next one.
+pub fn run(server_repo: &ServerRepo, args: &DaemonArgs, config: &Config, hooks: &Hooks) {
+    let mut daemon = Daemon::new(server_repo, args, config, hooks);
+    if let Some(addr) = &args.status_addr {
+        status::serve(addr, daemon.status.clone());
+    }
+    loop {
+        if let Err(e) = daemon.iteration() {
+            error!("Iteration failed: {}", e);
+            daemon
+                .status
+                .lock()
+                .expect("status lock poisoned")
+                .report_error(e.to_string());
+        }
+        debug!("Sleeping for {}s...", args.poll_interval_secs);
+        sleep(Duration::from_secs(args.poll_interval_secs));
+    }
+}
@@ -0,0 +1,116 @@
+//! How likely each square is to be taken from a team soon
+
+use crate::*;
+
+/// Per-square risk (0 to 1) that a square controlled by a team is recaptured within a number of
+/// ticks. Computed for every square, so squares the team does not control yet show how long they
+/// would last if painted.
+#[derive(Debug, Clone)]
+pub struct DangerMap {
+    pub team: Team,
+    /// Indexed the same way as Map::squares
+    pub risk: Vec<Vec<f32>>,
+}
+
+impl DangerMap {
+    /// Combines how soon an enemy could reach each square with how often it has changed hands over
+    /// the game's timeline
+    pub fn new(game: &Game, team: Team, horizon_ticks: u32) -> Self {
+        let map = game.map();
+        let horizon = horizon_ticks as i32;
+        let enemies: Vec<Position> = map
+            .players
+            .iter()
+            .filter(|(name, _)| {
+                let player = &game.players[*name];
+                player.team != team && player.team != Team::Null && !player.inactive
+            })
+            .map(|(_, pos)| *pos)
+            .collect();
+        let flip_rates = flip_rates(&game.timeline);
+        let risk = flip_rates
+            .iter()
+            .enumerate()
+            .map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(x, flip_rate)| {
+                        let pos = Position::new(x as i32, y as i32);
+                        // An enemy that can walk here in d ticks gets d chances fewer to do so
+                        let reach = enemies
+                            .iter()
+                            .map(|enemy| enemy.distance(pos))
+                            .filter(|distance| *distance <= horizon)
+                            .map(|distance| (horizon + 1 - distance) as f32 / (horizon + 1) as f32)
+                            .fold(0.0, f32::max);
+                        let churn = 1.0 - (1.0 - flip_rate).powi(horizon);
+                        1.0 - (1.0 - reach) * (1.0 - churn)
+                    })
+                    .collect()
+            })
+            .collect();
+        Self { team, risk }
+    }
+
+    /// 0 for positions off the map
+    pub fn risk(&self, pos: Position) -> f32 {
+        if pos.x < 0 || pos.y < 0 {
+            return 0.0;
+        }
+        self.risk
+            .get(pos.y as usize)
+            .and_then(|row| row.get(pos.x as usize))
+            .copied()
+            .unwrap_or(0.0)
+    }
+}
+
+/// For each square, the fraction of ticks in the timeline where its controller changed
+fn flip_rates(timeline: &[Map]) -> Vec<Vec<f32>> {
+    let latest = timeline.last().expect("timeline empty");
+    let mut flips = vec![vec![0.0; latest.width()]; latest.height()];
+    let ticks = timeline.len().saturating_sub(1);
+    if ticks == 0 {
+        return flips;
+    }
+    for pair in timeline.windows(2) {
+        for pos in latest.positions() {
+            if pair[0].controller_of(pos) != pair[1].controller_of(pos) {
+                flips[pos.y as usize][pos.x as usize] += 1.0;
+            }
+        }
+    }
+    for count in flips.iter_mut().flatten() {
+        *count /= ticks as f32;
+    }
+    flips
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures::{game, map};
+
+    #[test]
+    fn danger() {
+        let players = [("enemy", 0, 0)];
+        let timeline = vec![
+            map(&["rrrr", "rrgr"], &players),
+            map(&["rrrr", "rrrr"], &players),
+        ];
+        let game = game(&[("enemy", Team::Green)], timeline);
+        let danger = DangerMap::new(&game, Team::Red, 2);
+        // Right next to the enemy
+        assert!((danger.risk(Position::new(1, 0)) - 2.0 / 3.0).abs() < 0.001);
+        // Out of reach, and never changed hands
+        assert_eq!(danger.risk(Position::new(3, 0)), 0.0);
+        // Out of reach, but changed hands every tick
+        assert_eq!(danger.risk(Position::new(2, 1)), 1.0);
+        assert_eq!(danger.risk(Position::new(-1, 0)), 0.0);
+        // Our own players are not a danger to us
+        assert_eq!(
+            DangerMap::new(&game, Team::Green, 2).risk(Position::new(1, 0)),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,253 @@
+//! Exporting what every player saw, did and gained over server history, for training move policies
+//! outside of the bot
+
+use crate::predict::direction_between;
+use crate::*;
+use log::info;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::str::FromStr;
+
+/// Window cells are the sum of one of these and PLAYER_CELL if someone is standing there
+const OFF_MAP_CELL: u8 = 0;
+const UNCLAIMED_CELL: u8 = 1;
+const OWN_CELL: u8 = 2;
+const ENEMY_CELL: u8 = 3;
+const PLAYER_CELL: u8 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DatasetFormat {
+    Ndjson,
+    Csv,
+}
+
+impl FromStr for DatasetFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ndjson" => Ok(Self::Ndjson),
+            "csv" => Ok(Self::Csv),
+            other => Err(format!("unknown dataset format {:?}", other)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DatasetArgs {
+    pub history: u32,
+    /// How many squares around the player the window reaches in each direction
+    pub radius: u32,
+    /// How many ticks after the move territory change is measured over
+    pub outcome_ticks: usize,
+    pub format: DatasetFormat,
+    /// Standard output if None
+    pub output: Option<OsString>,
+}
+
+/// One move by one player
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Record {
+    /// Index into the timeline of the map the player moved from
+    pub tick: usize,
+    pub player: String,
+    pub team: Team,
+    pub x: i32,
+    pub y: i32,
+    /// The move before this one, None on the first map or if the player was not on the map
+    pub previous: Option<String>,
+    pub action: String,
+    /// Squares the player's team controls outcome_ticks after the move minus before it
+    pub territory_change: i64,
+    /// The squares around the player row by row, relative to their team (see the *_CELL constants)
+    pub window: Vec<u8>,
+}
+
+fn window(map: &Map, center: Position, team: Team, radius: i32) -> Vec<u8> {
+    let mut cells = Vec::new();
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            let cell = match map.square(center + Position::new(dx, dy)) {
+                None => OFF_MAP_CELL,
+                Some(square) => {
+                    let owner = match square.controlled_by {
+                        Team::Null => UNCLAIMED_CELL,
+                        controller if controller == team => OWN_CELL,
+                        _ => ENEMY_CELL,
+                    };
+                    match square.occupied_by_player {
+                        Some(_) => owner + PLAYER_CELL,
+                        None => owner,
+                    }
+                }
+            };
+            cells.push(cell);
+        }
+    }
+    cells
+}
+
+/// Every move by every player in the timeline, from the first map on, that has outcome_ticks of
+/// history after it. Moves of more than one square (ex respawns) are skipped.
+pub fn records(game: &Game, radius: u32, outcome_ticks: usize) -> Vec<Record> {
+    let territory: Vec<BTreeMap<Team, i64>> = game
+        .timeline
+        .iter()
+        .map(|map| {
+            let mut counts = BTreeMap::new();
+            for (_, square) in map.iter_squares() {
+                *counts.entry(square.controlled_by).or_insert(0) += 1;
+            }
+            counts
+        })
+        .collect();
+    let count = |tick: usize, team: Team| territory[tick].get(&team).copied().unwrap_or(0);
+    let mut records = Vec::new();
+    // The move itself needs the map after it even if outcome_ticks is 0
+    let last_tick = game.timeline.len().saturating_sub(outcome_ticks.max(1));
+    for tick in 0..last_tick {
+        let map = &game.timeline[tick];
+        let after = &game.timeline[tick + 1];
+        let mut names: Vec<&String> = map.players.keys().collect();
+        names.sort();
+        for name in names {
+            let team = match game.players.get(name) {
+                Some(player) => player.team,
+                None => continue,
+            };
+            let pos = map.players[name];
+            let action = match after
+                .players
+                .get(name)
+                .and_then(|after_pos| direction_between(pos, *after_pos))
+            {
+                Some(action) => action,
+                None => continue,
+            };
+            let previous = tick
+                .checked_sub(1)
+                .and_then(|before| game.timeline[before].players.get(name))
+                .and_then(|before_pos| direction_between(*before_pos, pos));
+            records.push(Record {
+                tick,
+                player: name.clone(),
+                team,
+                x: pos.x,
+                y: pos.y,
+                previous: previous.map(|previous| previous.to_string()),
+                action: action.to_string(),
+                territory_change: count(tick + outcome_ticks, team) - count(tick, team),
+                window: window(map, pos, team, radius as i32),
+            });
+        }
+    }
+    records
+}
+
+pub fn write_records(
+    records: &[Record],
+    format: DatasetFormat,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn Error>> {
+    match format {
+        DatasetFormat::Ndjson => {
+            for record in records {
+                serde_json::to_writer(&mut *out, record)?;
+                writeln!(out)?;
+            }
+        }
+        DatasetFormat::Csv => {
+            let cells = records.first().map_or(0, |record| record.window.len());
+            let window_columns: Vec<String> = (0..cells).map(|i| format!("w{}", i)).collect();
+            write!(out, "tick,player,team,x,y,previous,action,territory_change")?;
+            writeln!(out, ",{}", window_columns.join(","))?;
+            for record in records {
+                let window: Vec<String> = record.window.iter().map(u8::to_string).collect();
+                writeln!(
+                    out,
+                    "{},{},{:?},{},{},{},{},{},{}",
+                    record.tick,
+                    record.player,
+                    record.team,
+                    record.x,
+                    record.y,
+                    record.previous.as_deref().unwrap_or(""),
+                    record.action,
+                    record.territory_change,
+                    window.join(",")
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn export(game: &Game, args: &DatasetArgs) -> Result<(), Box<dyn Error>> {
+    let records = records(game, args.radius, args.outcome_ticks);
+    match &args.output {
+        Some(path) => {
+            let mut out = BufWriter::new(File::create(path)?);
+            write_records(&records, args.format, &mut out)?;
+            out.flush()?;
+            info!(
+                "Wrote {} records to {}",
+                records.len(),
+                path.to_string_lossy()
+            );
+        }
+        None => write_records(&records, args.format, &mut io::stdout().lock())?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures::{game, map};
+
+    #[test]
+    fn records_for_a_walk() {
+        let timeline = vec![
+            map(&["r.."], &[("a", 0, 0)]),
+            map(&["rr."], &[("a", 1, 0)]),
+            map(&["rrr"], &[("a", 2, 0)]),
+            map(&["rrr"], &[("a", 2, 0)]),
+        ];
+        let game = game(&[("a", Team::Red)], timeline);
+        let records = records(&game, 1, 1);
+        assert_eq!(records.len(), 3);
+        assert_eq!((records[0].tick, records[0].previous.as_deref()), (0, None));
+        assert_eq!(records[0].action, "right");
+        let first = &records[1];
+        assert_eq!((first.tick, first.x), (1, 1));
+        assert_eq!(first.previous.as_deref(), Some("right"));
+        assert_eq!(first.action, "right");
+        assert_eq!(first.territory_change, 1);
+        assert_eq!(
+            first.window,
+            vec![
+                0,
+                0,
+                0,
+                OWN_CELL,
+                OWN_CELL + PLAYER_CELL,
+                UNCLAIMED_CELL,
+                0,
+                0,
+                0
+            ]
+        );
+        assert_eq!(records[2].action, "idle");
+        assert_eq!(records[2].territory_change, 0);
+        let mut csv = Vec::new();
+        write_records(&records[1..2], DatasetFormat::Csv, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "tick,player,team,x,y,previous,action,territory_change,w0,w1,w2,w3,w4,w5,w6,w7,w8\n\
+             1,a,Red,1,0,right,right,1,0,0,0,2,6,1,0,0,0\n"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Letting a separate program pick our moves. The program gets the game as JSON on stdin and
+//! writes a direction (idle, up, down, left or right) on the first line of stdout.
+
+use crate::*;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::process::Stdio;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalConfig {
+    /// Run with `sh -c`
+    pub command: String,
+    /// How long to wait for an answer before falling back to the built in AI
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_timeout_ms() -> u64 {
+    2000
+}
+
+#[derive(Serialize)]
+struct PlayerPayload<'a> {
+    name: &'a str,
+    team: Team,
+    position: Position,
+    inactive: bool,
+}
+
+/// What the program gets on stdin
+#[derive(Serialize)]
+struct Payload<'a> {
+    us: &'a str,
+    team: Team,
+    position: Position,
+    width: usize,
+    height: usize,
+    /// Controlling team of each square, indexed [y][x]
+    map: Vec<Vec<Team>>,
+    /// Every player on the map, sorted by name
+    players: Vec<PlayerPayload<'a>>,
+}
+
+fn payload(game: &Game) -> Payload<'_> {
+    let map = game.map();
+    let mut players: Vec<PlayerPayload> = map
+        .players
+        .iter()
+        .filter_map(|(name, pos)| {
+            let player = game.players.get(name)?;
+            Some(PlayerPayload {
+                name,
+                team: player.team,
+                position: *pos,
+                inactive: player.inactive,
+            })
+        })
+        .collect();
+    players.sort_by_key(|player| player.name);
+    Payload {
+        us: game.us.as_deref().expect("we do not have a player"),
+        team: game.our_team(),
+        position: game.our_position(),
+        width: map.width(),
+        height: map.height(),
+        map: map
+            .squares
+            .iter()
+            .map(|row| row.iter().map(|square| square.controlled_by).collect())
+            .collect(),
+        players,
+    }
+}
+
+/// Asks the program which way we should move. The program is killed if it takes too long.
+pub fn decide(game: &Game, config: &ExternalConfig) -> Result<Direction, Box<dyn Error>> {
+    let input = serde_json::to_string(&payload(game))?;
+    debug!("Running external strategy `{}`", config.command);
+    let mut child = process::shell(&config.command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("child has no stdin");
+    let mut stdout = child.stdout.take().expect("child has no stdout");
+    // Both ends of the pipe get their own thread so a program that never reads or never writes can
+    // not block us past the timeout
+    thread::spawn(move || {
+        let _ = stdin.write_all(input.as_bytes());
+    });
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = String::new();
+        let result = stdout.read_to_string(&mut output).map(|_| output);
+        let _ = sender.send(result);
+    });
+    let output = match receiver.recv_timeout(Duration::from_millis(config.timeout_ms)) {
+        Ok(output) => output?,
+        Err(_) => {
+            // Killing the whole group closes the pipe, which lets the reader thread finish
+            process::kill(&mut child);
+            return Err(format!("timed out after {}ms", config.timeout_ms).into());
+        }
+    };
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("exited with {}", status).into());
+    }
+    let answer = output.lines().next().unwrap_or("").trim();
+    let direction = answer.parse()?;
+    info!("External strategy chose {}", direction);
+    Ok(direction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures;
+
+    fn game() -> Game {
+        let map = fixtures::map(&["...", "..."], &[("us", 1, 0)]);
+        let mut game = fixtures::game(&[("us", Team::Red)], vec![map]);
+        game.us = Some("us".to_owned());
+        game
+    }
+
+    fn run(command: &str) -> Result<Direction, Box<dyn Error>> {
+        let config = ExternalConfig {
+            command: command.to_owned(),
+            timeout_ms: 500,
+        };
+        decide(&game(), &config)
+    }
+
+    #[test]
+    fn program_gets_the_game_and_picks_a_direction() {
+        let direction =
+            run(r#"grep -q '"us":"us","team":"Red","position":{"x":1,"y":0}' && echo left"#);
+        assert_eq!(direction.unwrap(), Direction::Left);
+    }
+
+    #[test]
+    fn bad_programs_are_errors() {
+        assert!(run("echo sideways").is_err());
+        assert!(run("exit 1").is_err());
+        assert!(run("sleep 5; echo up").is_err());
+    }
+}
@@ -0,0 +1,82 @@
+//! Where territory of different teams meets
+
+use crate::*;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrontierSquare {
+    pub pos: Position,
+    pub team: Team,
+    /// The other teams (including Null) controlling squares next to this one
+    pub borders: Vec<Team>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Frontier {
+    /// Every square controlled by a team that is next to a square controlled by someone else
+    pub squares: Vec<FrontierSquare>,
+    /// Number of edges between squares of each pair of teams, with the lower team first
+    pub pair_counts: BTreeMap<(Team, Team), usize>,
+}
+
+impl Frontier {
+    /// Squares controlled by other teams that border the given team, which is where it can expand
+    pub fn bordering(&self, team: Team) -> impl Iterator<Item = &FrontierSquare> {
+        self.squares
+            .iter()
+            .filter(move |square| square.team != team && square.borders.contains(&team))
+    }
+}
+
+impl Map {
+    pub fn frontier(&self) -> Frontier {
+        let mut frontier = Frontier::default();
+        for (pos, square) in self.iter_squares() {
+            let team = square.controlled_by;
+            let mut borders = Vec::new();
+            for next in self.neighbors(pos) {
+                let other = self.controller_of(next);
+                if other == team {
+                    continue;
+                }
+                // Only count each edge from one side
+                if team < other {
+                    *frontier.pair_counts.entry((team, other)).or_insert(0) += 1;
+                }
+                if !borders.contains(&other) {
+                    borders.push(other);
+                }
+            }
+            if team != Team::Null && !borders.is_empty() {
+                borders.sort();
+                frontier.squares.push(FrontierSquare { pos, team, borders });
+            }
+        }
+        frontier
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures::map;
+
+    #[test]
+    fn frontier() {
+        let map = map(&["rrg", "r.g"], &[]);
+        let frontier = map.frontier();
+        assert_eq!(frontier.pair_counts[&(Team::Red, Team::Green)], 1);
+        assert_eq!(frontier.pair_counts[&(Team::Null, Team::Red)], 2);
+        assert_eq!(frontier.pair_counts[&(Team::Null, Team::Green)], 1);
+        // Every claimed square except the top left corner is on the frontier
+        assert_eq!(frontier.squares.len(), 4);
+        let top_right = frontier
+            .squares
+            .iter()
+            .find(|square| square.pos == Position::new(2, 0))
+            .unwrap();
+        assert_eq!(top_right.borders, vec![Team::Red]);
+        let bordering_red: Vec<Position> = frontier.bordering(Team::Red).map(|s| s.pos).collect();
+        assert_eq!(bordering_red, vec![Position::new(2, 0)]);
+    }
+}
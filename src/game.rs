@@ -1,7 +1,12 @@
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
 use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+/// Players that have not moved for this many ticks are considered inactive
+pub const INACTIVE_TICKS: usize = 10;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Direction {
     Null,
     Up,
@@ -22,7 +27,49 @@ impl std::fmt::Display for Direction {
     }
 }
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+/// Parses the same words Display writes, which are what the act file uses
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|direction| direction.to_string() == s)
+            .ok_or_else(|| format!("unknown direction {:?}", s))
+    }
+}
+
+impl Direction {
+    /// Every direction, staying put first
+    pub const ALL: [Direction; 5] = [
+        Direction::Null,
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+    /// Every direction that actually moves
+    pub const MOVES: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    /// The change in position moving this way makes
+    pub fn vector(self) -> Position {
+        match self {
+            Self::Null => Position::new(0, 0),
+            Self::Up => Position::new(0, -1),
+            Self::Down => Position::new(0, 1),
+            Self::Left => Position::new(-1, 0),
+            Self::Right => Position::new(1, 0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Team {
     Null,
     Red,
@@ -44,7 +91,7 @@ impl FromStr for Team {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
@@ -64,6 +111,11 @@ impl Position {
     pub fn distance(&self, other: Position) -> i32 {
         (self.x - other.x).abs() + (self.y - other.y).abs()
     }
+
+    /// Where moving one square in a direction would end up (which may be off the map)
+    pub fn step(self, direction: Direction) -> Position {
+        self + direction.vector()
+    }
 }
 
 impl std::ops::Add<Position> for Position {
@@ -74,13 +126,23 @@ impl std::ops::Add<Position> for Position {
     }
 }
 
-#[derive(Debug)]
+impl std::ops::Sub<Position> for Position {
+    type Output = Position;
+
+    fn sub(self, rhs: Position) -> Position {
+        Position::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Player {
     pub team: Team,
     pub name: String,
+    /// Has not moved for INACTIVE_TICKS ticks, so is treated as an obstacle rather than a threat
+    pub inactive: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Square {
     pub controlled_by: Team,
     pub occupied_by_player: Option<String>,
@@ -95,10 +157,22 @@ impl Square {
     }
 }
 
-#[derive(Debug)]
+/// A player left off the map because their position made no sense
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Quarantined {
+    pub name: String,
+    pub position: Position,
+    pub reason: String,
+}
+
+/// Serialized as a MapData, so deserializing goes through Map::new
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "MapData", try_from = "MapData")]
 pub struct Map {
     pub players: HashMap<String, Position>,
     pub squares: Vec<Vec<Square>>,
+    /// Players that were given to Map::new but are not in players
+    pub quarantine: Vec<Quarantined>,
     width: usize,
     height: usize,
 }
@@ -124,24 +198,72 @@ impl Map {
             }
         }
         let mut map = Self {
-            players,
+            players: HashMap::new(),
             squares,
+            quarantine: Vec::new(),
             width,
             height,
         };
-        for (name, pos) in &map.players {
-            let mut square = &mut map.squares[pos.y as usize][pos.x as usize];
-            if let Some(player) = &square.occupied_by_player {
-                return Err(format!(
-                    "{} is occupied by both {} and {}",
-                    pos, player, name
-                ));
+        // Sorted so the same player wins a shared square every time
+        let mut players: Vec<(String, Position)> = players.into_iter().collect();
+        players.sort();
+        for (name, pos) in players {
+            let reason = if !map.is_inside(pos) {
+                Some(format!("{} is outside the {}x{} map", pos, width, height))
+            } else {
+                map.squares[pos.y as usize][pos.x as usize]
+                    .occupied_by_player
+                    .as_ref()
+                    .map(|other| format!("{} is already occupied by {}", pos, other))
+            };
+            match reason {
+                Some(reason) => map.quarantine.push(Quarantined {
+                    name,
+                    position: pos,
+                    reason,
+                }),
+                None => {
+                    map.squares[pos.y as usize][pos.x as usize].occupied_by_player =
+                        Some(name.clone());
+                    map.players.insert(name, pos);
+                }
             }
-            square.occupied_by_player = Some(name.to_string());
         }
         Ok(map)
     }
 
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Every position on the map, row by row
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let width = self.width;
+        (0..self.height)
+            .flat_map(move |y| (0..width).map(move |x| Position::new(x as i32, y as i32)))
+    }
+
+    /// Every square on the map with its position, row by row
+    pub fn iter_squares(&self) -> impl Iterator<Item = (Position, &Square)> {
+        self.squares.iter().enumerate().flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(move |(x, square)| (Position::new(x as i32, y as i32), square))
+        })
+    }
+
+    /// The positions next to pos that are on the map
+    pub fn neighbors(&self, pos: Position) -> impl Iterator<Item = Position> + '_ {
+        Direction::MOVES
+            .iter()
+            .map(move |direction| pos.step(*direction))
+            .filter(move |next| self.is_inside(*next))
+    }
+
     pub fn is_inside(&self, pos: Position) -> bool {
         pos.x >= 0 && (pos.x as usize) < self.width && pos.y >= 0 && (pos.y as usize) < self.height
     }
@@ -161,11 +283,62 @@ impl Map {
     }
 }
 
-#[derive(Debug)]
+/// How a Map is written to snapshots. Square::occupied_by_player and the size are left out since
+/// they follow from the rest.
+#[derive(Serialize, Deserialize)]
+struct MapData {
+    players: BTreeMap<String, Position>,
+    /// Who controls each square
+    squares: Vec<Vec<Team>>,
+    quarantine: Vec<Quarantined>,
+}
+
+impl From<Map> for MapData {
+    fn from(map: Map) -> Self {
+        Self {
+            players: map.players.into_iter().collect(),
+            squares: map
+                .squares
+                .iter()
+                .map(|row| row.iter().map(|square| square.controlled_by).collect())
+                .collect(),
+            quarantine: map.quarantine,
+        }
+    }
+}
+
+impl TryFrom<MapData> for Map {
+    type Error = String;
+
+    fn try_from(data: MapData) -> Result<Self, Self::Error> {
+        let squares = data
+            .squares
+            .into_iter()
+            .map(|row| row.into_iter().map(Square::new).collect())
+            .collect();
+        let mut map = Map::new(data.players.into_iter().collect(), squares)?;
+        map.quarantine.extend(data.quarantine);
+        Ok(map)
+    }
+}
+
+/// Writes a HashMap sorted by key, so the same game always serializes the same way
+fn serialize_sorted<S: Serializer, V: Serialize>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Game {
     pub us: Option<String>,
+    /// Everyone in the timeline, including players that have since left (see current_players)
+    #[serde(serialize_with = "serialize_sorted")]
     pub players: HashMap<String, Player>,
     pub timeline: Vec<Map>,
+    /// Problems that were skipped over while loading
+    pub load_warnings: Vec<String>,
 }
 
 impl Game {
@@ -189,4 +362,149 @@ impl Game {
     pub fn map(&self) -> &Map {
         self.timeline.last().expect("timeline empty")
     }
+
+    /// Players in the latest map, including quarantined ones but not players who have left
+    pub fn current_players(&self) -> impl Iterator<Item = &Player> {
+        let map = self.map();
+        map.players
+            .keys()
+            .chain(map.quarantine.iter().map(|quarantined| &quarantined.name))
+            .filter_map(move |name| self.players.get(name))
+    }
+
+    /// Sets Player::inactive from the timeline. Nobody is inactive if there is not enough history
+    /// to tell.
+    pub fn mark_inactive_players(&mut self) {
+        let recent = match self.timeline.len().checked_sub(INACTIVE_TICKS + 1) {
+            Some(start) => &self.timeline[start..],
+            None => &[],
+        };
+        for (name, player) in &mut self.players {
+            let mut positions = recent.iter().map(|map| map.players.get(name));
+            player.inactive = match positions.next() {
+                Some(Some(first)) => positions.all(|pos| pos == Some(first)),
+                _ => false,
+            };
+        }
+    }
+}
+
+/// Building maps and games for tests
+#[cfg(test)]
+pub mod fixtures {
+    use super::*;
+
+    /// A map from rows of squares (r, g and b are controlled by that team, anything else is
+    /// unclaimed) with players at the given x and y
+    pub fn map(rows: &[&str], players: &[(&str, i32, i32)]) -> Map {
+        let squares = rows
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .map(|c| {
+                        Square::new(match c {
+                            'r' => Team::Red,
+                            'g' => Team::Green,
+                            'b' => Team::Blue,
+                            _ => Team::Null,
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+        let players = players
+            .iter()
+            .map(|(name, x, y)| (name.to_string(), Position::new(*x, *y)))
+            .collect();
+        Map::new(players, squares).expect("invalid map")
+    }
+
+    /// A game over the given timeline with players on the given teams. Nobody is inactive and we
+    /// are not anyone.
+    pub fn game(players: &[(&str, Team)], timeline: Vec<Map>) -> Game {
+        let players = players
+            .iter()
+            .map(|(name, team)| {
+                let player = Player {
+                    team: *team,
+                    name: name.to_string(),
+                    inactive: false,
+                };
+                (name.to_string(), player)
+            })
+            .collect();
+        Game {
+            us: None,
+            players,
+            timeline,
+            load_warnings: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geometry() {
+        let pos = Position::new(1, 1);
+        assert_eq!(pos.step(Direction::Up), Position::new(1, 0));
+        assert_eq!(pos.step(Direction::Null), pos);
+        assert_eq!(Position::new(3, 1) - pos, Position::new(2, 0));
+        let map = fixtures::map(&["...", "..."], &[]);
+        assert_eq!((map.width(), map.height()), (3, 2));
+        assert_eq!(map.positions().count(), 6);
+        assert_eq!(map.positions().nth(4), Some(Position::new(1, 1)));
+        assert_eq!(
+            map.iter_squares().map(|(pos, _)| pos).collect::<Vec<_>>(),
+            map.positions().collect::<Vec<_>>()
+        );
+        let neighbors: Vec<Position> = map.neighbors(Position::new(0, 0)).collect();
+        assert_eq!(neighbors, vec![Position::new(0, 1), Position::new(1, 0)]);
+    }
+
+    #[test]
+    fn bad_players_are_quarantined() {
+        let players = [("a", 1, 1), ("b", 1, 1), ("c", -1, 0), ("d", 0, 2)];
+        let map = fixtures::map(&["...", "..."], &players);
+        assert_eq!(map.players.len(), 1);
+        assert_eq!(map.squares[1][1].occupied_by_player.as_deref(), Some("a"));
+        let quarantined: Vec<&str> = map.quarantine.iter().map(|q| q.name.as_str()).collect();
+        assert_eq!(quarantined, vec!["b", "c", "d"]);
+        assert_eq!(map.quarantine[0].reason, "(1, 1) is already occupied by a");
+        assert_eq!(map.quarantine[1].reason, "(-1, 0) is outside the 3x2 map");
+    }
+
+    #[test]
+    fn current_players() {
+        let timeline = vec![
+            fixtures::map(&["..."], &[("left", 0, 0), ("stayed", 1, 0)]),
+            fixtures::map(&["..."], &[("stayed", 1, 0), ("lost", 5, 0)]),
+        ];
+        let teams = [
+            ("left", Team::Red),
+            ("stayed", Team::Red),
+            ("lost", Team::Blue),
+        ];
+        let game = fixtures::game(&teams, timeline);
+        let mut names: Vec<&str> = game.current_players().map(|p| p.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["lost", "stayed"]);
+    }
+
+    #[test]
+    fn inactive_players() {
+        let map = |moving_x: i32| {
+            fixtures::map(&["...", "..."], &[("still", 0, 0), ("moving", moving_x, 1)])
+        };
+        let timeline = (0..INACTIVE_TICKS).map(|_| map(0)).collect();
+        let mut game = fixtures::game(&[("still", Team::Red), ("moving", Team::Red)], timeline);
+        game.mark_inactive_players();
+        assert!(!game.players["still"].inactive);
+        game.timeline.push(map(1));
+        game.mark_inactive_players();
+        assert!(game.players["still"].inactive);
+        assert!(!game.players["moving"].inactive);
+    }
 }
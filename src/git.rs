@@ -1,16 +1,43 @@
-use git2::{BranchType, Commit, ObjectType, Repository, Tree, TreeEntry};
+use git2::{BranchType, Commit, Oid, Repository, Tree, TreeEntry};
+use log::{debug, info, warn};
 use std::collections::HashMap;
 use std::error::Error;
-use std::ffi::OsStr;
+use std::ffi::{OsStr, OsString};
+use std::process;
 use std::str::from_utf8;
 use std::str::FromStr;
 
 use crate::{Game, Map, Player, Position, Square, Team};
 
 pub struct ServerRepo {
+    path: OsString,
     repo: Repository,
 }
 
+pub struct ClientRepo {
+    path: OsString,
+}
+
+/// Runs the git command line tool in the given repo, so pulls and pushes use the user's normal
+/// git and SSH config
+pub fn run_git(repo_path: &OsStr, args: &[&str]) -> Result<process::ExitStatus, Box<dyn Error>> {
+    let status = process::Command::new("git")
+        .arg("-C")
+        .arg(repo_path)
+        .args(args)
+        .status()?;
+    Ok(status)
+}
+
+pub fn run_git_checked(repo_path: &OsStr, args: &[&str]) -> Result<(), Box<dyn Error>> {
+    let status = run_git(repo_path, args)?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("`git {}` failed with {}", args.join(" "), status).into())
+    }
+}
+
 #[derive(Debug)]
 struct PlayerData {
     name: String,
@@ -58,9 +85,31 @@ impl FromStr for MapData {
 
 impl ServerRepo {
     pub fn new(path: &OsStr) -> Result<Self, Box<dyn Error>> {
-        eprintln!("Trying to open server repo at {}", path.to_string_lossy());
+        debug!("Trying to open server repo at {}", path.to_string_lossy());
         let repo = Repository::open(path)?;
-        Ok(ServerRepo { repo })
+        Ok(ServerRepo {
+            path: path.to_owned(),
+            repo,
+        })
+    }
+
+    pub fn pull(&self) -> Result<(), Box<dyn Error>> {
+        debug!("Pulling server repo");
+        run_git_checked(&self.path, &["pull"])
+    }
+
+    fn master_commit(&self) -> Result<Commit<'_>, Box<dyn Error>> {
+        let master = self.repo.find_branch("master", BranchType::Local)?;
+        Ok(master.into_reference().peel_to_commit()?)
+    }
+
+    pub fn master_oid(&self) -> Result<Oid, Box<dyn Error>> {
+        Ok(self.master_commit()?.id())
+    }
+
+    /// Seconds since the epoch that the commit was made
+    pub fn commit_time(&self, oid: Oid) -> Result<i64, Box<dyn Error>> {
+        Ok(self.repo.find_commit(oid)?.time().seconds())
     }
 
     fn load_contents_of_file(&self, tree: &Tree, name: &str) -> Result<String, Box<dyn Error>> {
@@ -95,7 +144,12 @@ impl ServerRepo {
         })
     }
 
-    fn load_players_from_commit(&self, commit: &Commit) -> Result<Vec<PlayerData>, Box<dyn Error>> {
+    /// Players that fail to load are skipped, with a message added to warnings
+    fn load_players_from_commit(
+        &self,
+        commit: &Commit,
+        warnings: &mut Vec<String>,
+    ) -> Result<Vec<PlayerData>, Box<dyn Error>> {
         let root_tree = commit.tree()?;
         let players_tree_entry = root_tree
             .get_name("players")
@@ -105,11 +159,15 @@ impl ServerRepo {
         for player_tree_entry in players_tree.iter() {
             match self.load_player_from_tree_entry(&player_tree_entry) {
                 Ok(player) => players.push(player),
-                Err(e) => eprintln!(
-                    "Failed to load player {}: {}",
-                    player_tree_entry.name().unwrap_or("[no name]"),
-                    e
-                ),
+                Err(e) => {
+                    let warning = format!(
+                        "Failed to load player {}: {}",
+                        player_tree_entry.name().unwrap_or("[no name]"),
+                        e
+                    );
+                    warn!("{}", warning);
+                    warnings.push(warning);
+                }
             }
         }
         Ok(players)
@@ -121,36 +179,111 @@ impl ServerRepo {
         Ok(map.parse()?)
     }
 
-    pub fn load_game(&self, _history_limit: Option<u32>) -> Result<Game, Box<dyn Error>> {
-        eprintln!("Loading game from repo");
-        let master = self.repo.find_branch("master", BranchType::Local)?;
-        let last_commit = master.into_reference().peel_to_commit()?;
-        let player_data = self.load_players_from_commit(&last_commit)?;
-        let map_data = self.load_map_from_commit(&last_commit)?;
-        let mut game_players = HashMap::new();
+    fn load_map_from_commit_with_players(
+        &self,
+        commit: &Commit,
+        players: &mut HashMap<String, Player>,
+        warnings: &mut Vec<String>,
+    ) -> Result<Map, Box<dyn Error>> {
+        let player_data = self.load_players_from_commit(commit, warnings)?;
+        let map_data = self.load_map_from_commit(commit)?;
         let mut map_players = HashMap::new();
         for player in player_data {
             map_players.insert(player.name.clone(), player.position);
-            game_players.insert(
-                player.name.clone(),
-                Player {
-                    team: player.team,
-                    name: player.name,
-                },
-            );
+            // We load newest first, and a player's latest team is the one that matters
+            players.entry(player.name.clone()).or_insert(Player {
+                team: player.team,
+                name: player.name,
+                inactive: false,
+            });
         }
         let squares = map_data
             .squares
             .into_iter()
             .map(|row| row.into_iter().map(Square::new).collect())
             .collect();
-        let map = Map::new(map_players, squares)?;
-        let timeline = vec![map];
-        let game = Game {
+        Ok(Map::new(map_players, squares)?)
+    }
+
+    /// Loads up to history_limit maps (all of history if None) following first parents back from
+    /// master. Only problems with the latest commit are fatal, history stops at the first older
+    /// commit that fails to load.
+    pub fn load_game(&self, history_limit: Option<u32>) -> Result<Game, Box<dyn Error>> {
+        debug!("Loading game from repo");
+        let last_commit = self.master_commit()?;
+        let mut load_warnings = Vec::new();
+        let mut game_players = HashMap::new();
+        let latest = self.load_map_from_commit_with_players(
+            &last_commit,
+            &mut game_players,
+            &mut load_warnings,
+        )?;
+        for quarantined in &latest.quarantine {
+            let warning = format!(
+                "Quarantined player {}: {}",
+                quarantined.name, quarantined.reason
+            );
+            warn!("{}", warning);
+            load_warnings.push(warning);
+        }
+        let mut timeline = vec![latest];
+        let mut commit = last_commit;
+        while history_limit.is_none_or(|limit| timeline.len() < limit as usize) {
+            commit = match commit.parents().next() {
+                Some(parent) => parent,
+                None => break,
+            };
+            let mut old_warnings = Vec::new();
+            match self.load_map_from_commit_with_players(
+                &commit,
+                &mut game_players,
+                &mut old_warnings,
+            ) {
+                Ok(map) => timeline.push(map),
+                Err(e) => {
+                    debug!("Stopping history at {}: {}", commit.id(), e);
+                    break;
+                }
+            }
+        }
+        timeline.reverse();
+        debug!("Loaded {} maps of history", timeline.len());
+        let mut game = Game {
             us: None,
             players: game_players,
             timeline,
+            load_warnings,
         };
+        game.mark_inactive_players();
         Ok(game)
     }
 }
+
+impl ClientRepo {
+    pub fn new(path: &OsStr) -> Self {
+        ClientRepo {
+            path: path.to_owned(),
+        }
+    }
+
+    /// If the act file differs from what is committed
+    pub fn act_changed(&self) -> Result<bool, Box<dyn Error>> {
+        let status = run_git(&self.path, &["diff", "--quiet", "--exit-code", "act"])?;
+        match status.code() {
+            Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            _ => Err(format!("checking for changes to act failed with {}", status).into()),
+        }
+    }
+
+    pub fn commit_act(&self, message: &str) -> Result<(), Box<dyn Error>> {
+        info!("Committing {:?}", message);
+        run_git_checked(&self.path, &["add", "act"])?;
+        run_git_checked(&self.path, &["commit", "-m", message])
+    }
+
+    pub fn push(&self, remote: &str) -> Result<(), Box<dyn Error>> {
+        info!("Pushing to {}", remote);
+        run_git_checked(&self.path, &["push", remote, "master"])
+    }
+}
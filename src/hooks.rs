@@ -0,0 +1,158 @@
+use crate::config::HooksConfig;
+use crate::process;
+use crate::status::unix_time;
+use log::{error, info};
+use serde::Serialize;
+use serde_json::Value;
+use std::io::Write;
+use std::panic;
+use std::process::Stdio;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    Panic,
+    LoadFailure,
+    PushFailure,
+    PlayerMissing,
+    EnemiesInZone,
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    event: Event,
+    time: u64,
+    message: &'a str,
+    details: Value,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Hooks {
+    config: HooksConfig,
+}
+
+impl Hooks {
+    pub fn new(config: HooksConfig) -> Self {
+        Self { config }
+    }
+
+    fn command_for(&self, event: Event) -> Option<&str> {
+        match event {
+            Event::Panic | Event::LoadFailure => self.config.on_failure.as_deref(),
+            Event::PushFailure => self.config.on_push_failure.as_deref(),
+            Event::PlayerMissing => self.config.on_player_missing.as_deref(),
+            Event::EnemiesInZone => self.config.on_enemies_in_zone.as_deref(),
+        }
+    }
+
+    /// Runs the hook for the event (if there is one) and waits up to HooksConfig::timeout_secs for
+    /// it to finish. Hook failures are logged but otherwise ignored, since this often runs while
+    /// something else is going wrong.
+    pub fn fire(&self, event: Event, message: &str, details: Value) {
+        let command = match self.command_for(event) {
+            Some(command) => command,
+            None => return,
+        };
+        info!("Running {:?} hook `{}`", event, command);
+        let payload = Payload {
+            event,
+            time: unix_time(),
+            message,
+            details,
+        };
+        let payload = match serde_json::to_string(&payload) {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!("Failed to serialize hook payload: {}", e);
+                return;
+            }
+        };
+        let child = process::shell(command).stdin(Stdio::piped()).spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                error!("Failed to run hook `{}`: {}", command, e);
+                return;
+            }
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            if let Err(e) = writeln!(stdin, "{}", payload) {
+                error!("Failed to send payload to hook `{}`: {}", command, e);
+            }
+        }
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        match process::wait_timeout(&mut child, timeout) {
+            Ok(Some(status)) if !status.success() => {
+                error!("Hook `{}` failed with {}", command, status)
+            }
+            Ok(Some(_)) => (),
+            Ok(None) => error!(
+                "Hook `{}` was killed after {}s",
+                command, self.config.timeout_secs
+            ),
+            Err(e) => error!("Failed to wait for hook `{}`: {}", command, e),
+        }
+    }
+
+    /// Like fire, but on another thread so a slow hook can not hold up the caller
+    pub fn fire_in_background(&self, event: Event, message: String, details: Value) {
+        if self.command_for(event).is_none() {
+            return;
+        }
+        let hooks = self.clone();
+        thread::spawn(move || hooks.fire(event, &message, details));
+    }
+
+    /// Makes every panic fire the failure hook after the normal panic message is printed
+    pub fn install_panic_hook(&self) {
+        if self.config.on_failure.is_none() {
+            return;
+        }
+        let hooks = self.clone();
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            default_hook(info);
+            let message = if let Some(s) = info.payload().downcast_ref::<&str>() {
+                (*s).to_owned()
+            } else if let Some(s) = info.payload().downcast_ref::<String>() {
+                s.clone()
+            } else {
+                "unknown panic".to_owned()
+            };
+            let location = info.location().map(|l| l.to_string());
+            hooks.fire(
+                Event::Panic,
+                &message,
+                serde_json::json!({ "location": location }),
+            );
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn hook_gets_payload_on_stdin() {
+        let path = std::env::temp_dir().join(format!("hook-test-{}.json", std::process::id()));
+        let hooks = Hooks::new(HooksConfig {
+            on_player_missing: Some(format!("cat > '{}'", path.display())),
+            ..HooksConfig::default()
+        });
+        hooks.fire(
+            Event::PlayerMissing,
+            "wmww is not on the map",
+            serde_json::json!({ "player": "wmww" }),
+        );
+        let payload: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(payload["event"], "player_missing");
+        assert!(payload["time"].as_u64().unwrap() > 0);
+        assert_eq!(payload["message"], "wmww is not on the map");
+        assert_eq!(payload["details"], serde_json::json!({ "player": "wmww" }));
+    }
+}
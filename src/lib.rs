@@ -0,0 +1,44 @@
+//! Everything needed to write a gitland bot: the game model, loading it from the server repo,
+//! a simulator of the rules, map analysis and rendering. This bot's own AI, daemon and tooling are
+//! here too, so the binary is only argument parsing.
+
+pub mod act;
+pub mod ai;
+pub mod config;
+pub mod coord;
+pub mod daemon;
+pub mod danger;
+pub mod dataset;
+pub mod external;
+pub mod frontier;
+pub mod game;
+pub mod git;
+pub mod hooks;
+pub mod logging;
+pub mod metrics;
+pub mod path;
+pub mod predict;
+pub mod predict_eval;
+pub mod process;
+pub mod profile;
+pub mod region;
+pub mod script;
+pub mod show;
+pub mod sim;
+pub mod snapshot;
+pub mod status;
+pub mod visits;
+pub mod zone;
+
+pub use act::{act, Account};
+pub use config::Config;
+pub use game::*;
+pub use git::ServerRepo;
+use std::error::Error;
+
+/// How many server commits of history the bot loads each tick
+pub const HISTORY_LIMIT: u32 = 12;
+
+pub fn load_game(server_repo: &ServerRepo, history_limit: u32) -> Result<Game, Box<dyn Error>> {
+    server_repo.load_game(Some(history_limit))
+}
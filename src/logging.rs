@@ -0,0 +1,150 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde_json::json;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Module paths are shortened to this, so targets are things like `git` and `ai`
+const CRATE_PREFIX: &str = concat!(env!("CARGO_CRATE_NAME"), "::");
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(format!("unknown log format {:?}", other)),
+        }
+    }
+}
+
+/// A default level plus overrides for specific targets, parsed from something like `warn,ai=debug`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFilter {
+    default: LevelFilter,
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl FromStr for LogFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_level = |level: &str| {
+            LevelFilter::from_str(level).map_err(|_| format!("unknown log level {:?}", level))
+        };
+        // Quiet unless asked otherwise, so only problems show up in production logs
+        let mut filter = LogFilter {
+            default: LevelFilter::Warn,
+            targets: Vec::new(),
+        };
+        for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            if let Some((target, level)) = part.split_once('=') {
+                filter
+                    .targets
+                    .push((target.to_owned(), parse_level(level)?));
+            } else {
+                filter.default = parse_level(part)?;
+            }
+        }
+        Ok(filter)
+    }
+}
+
+impl LogFilter {
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .rev()
+            .find(|(name, _)| target == name || target.starts_with(&format!("{}::", name)))
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+struct Logger {
+    filter: LogFilter,
+    format: LogFormat,
+}
+
+fn short_target(target: &str) -> &str {
+    if target == env!("CARGO_CRATE_NAME") {
+        "main"
+    } else {
+        target.strip_prefix(CRATE_PREFIX).unwrap_or(target)
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level_for(short_target(metadata.target()))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let target = short_target(record.target());
+        let line = match self.format {
+            LogFormat::Text => match record.level() {
+                Level::Info => format!("[{}] {}", target, record.args()),
+                level => format!("[{}] {}: {}", target, level, record.args()),
+            },
+            LogFormat::Json => json!({
+                "time": crate::status::unix_time(),
+                "level": record.level().as_str(),
+                "target": target,
+                "message": record.args().to_string(),
+            })
+            .to_string(),
+        };
+        let _ = writeln!(std::io::stderr(), "{}", line);
+    }
+
+    fn flush(&self) {}
+}
+
+/// Will panic if called twice
+pub fn init(filter: LogFilter, format: LogFormat) {
+    log::set_max_level(filter.max_level());
+    log::set_boxed_logger(Box::new(Logger { filter, format })).expect("logger already set");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_with_targets() {
+        let filter: LogFilter = "warn,ai=debug,git=off".parse().expect("failed to parse");
+        assert_eq!(filter.level_for("act"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("ai"), LevelFilter::Debug);
+        assert_eq!(filter.level_for("ai::search"), LevelFilter::Debug);
+        assert_eq!(filter.level_for("git"), LevelFilter::Off);
+        assert_eq!(filter.max_level(), LevelFilter::Debug);
+        let filter: LogFilter = "ai=debug".parse().expect("failed to parse");
+        assert_eq!(filter.level_for("git"), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn invalid_level() {
+        assert!("ai=loud".parse::<LogFilter>().is_err());
+    }
+
+    #[test]
+    fn crate_prefix_is_stripped() {
+        assert_eq!(short_target(module_path!()), "logging::tests");
+    }
+}
@@ -1,31 +1,88 @@
 #[macro_use]
 extern crate clap;
 
-mod act;
-mod ai;
 mod arg;
-mod game;
-mod git;
-mod show;
 
-use act::act;
-use arg::{parse_arguments, ActArgs, Command};
-use game::*;
-use git::ServerRepo;
-use show::show;
+use arg::{parse_arguments, Command};
+use log::{debug, warn};
+use wmww_gitland_bot::coord::{Claim, Coordinator};
+use wmww_gitland_bot::hooks::Hooks;
+use wmww_gitland_bot::show::{show, show_frontier};
+use wmww_gitland_bot::snapshot::SnapshotArgs;
+use wmww_gitland_bot::*;
+
+/// Acts for every account, sharing claims with teammates if coordination is set up. Coordination is
+/// optional, so if the shared repo can not be reached we act without it.
+fn act_once(game: &mut Game, accounts: &[Account], config: &Config) {
+    let coordinator = config.coordination.clone().map(Coordinator::new);
+    let players: Vec<String> = accounts.iter().map(|a| a.player.clone()).collect();
+    let teammates = match &coordinator {
+        Some(coordinator) => coordinator.teammate_claims(&players).unwrap_or_else(|e| {
+            warn!("Failed to read teammate claims: {}", e);
+            Vec::new()
+        }),
+        None => Vec::new(),
+    };
+    let decisions = act(game, accounts, config, &teammates);
+    if let Some(coordinator) = &coordinator {
+        for (account, decision) in &decisions {
+            let claim = Claim::new(&account.player, decision, &config.ai.zone);
+            if let Err(e) = coordinator.publish(&claim) {
+                warn!("Failed to publish claim: {}", e);
+            }
+        }
+    }
+}
 
 fn main() {
     let args = parse_arguments();
-    eprintln!("Running with arguments: {:?}", args);
-    let server_repo =
-        ServerRepo::new(&args.server_repo_path).expect("failed to initialize server repo");
-    let mut game = server_repo
-        .load_game(Some(12))
+    logging::init(args.log_filter.clone(), args.log_format);
+    debug!("Running with arguments: {:?}", args);
+    let mut config = match &args.config_path {
+        Some(path) => Config::load(path).expect("failed to load config"),
+        None => Config::default(),
+    };
+    config.ai.init_seed();
+    let hooks = Hooks::new(config.hooks.clone());
+    hooks.install_panic_hook();
+    // Only opened by commands that use it, so snapshots work without a server repo
+    let open_server_repo = || {
+        let path = args
+            .server_repo_path
+            .as_ref()
+            .expect("no server repo given");
+        ServerRepo::new(path).expect("failed to initialize server repo")
+    };
+    let load = |history_limit| {
+        let mut game = match &args.snapshot_path {
+            Some(path) => snapshot::load(path, history_limit),
+            None => load_game(&open_server_repo(), history_limit),
+        }
         .expect("failed to load game");
-    game.us = Some("wmww".to_owned());
-    match args.command {
-        Command::Show => show(&game),
-        Command::Act(act_args) => act(&game, &act_args),
+        game.us = Some(args.player.clone());
+        game
+    };
+    match &args.command {
+        Command::Show(show_args) => show(&load(HISTORY_LIMIT), show_args, &config),
+        Command::Act(act_args) => act_once(&mut load(HISTORY_LIMIT), &act_args.accounts, &config),
+        Command::Frontier => show_frontier(&load(HISTORY_LIMIT)),
+        Command::Players => profile::show_players(&load(HISTORY_LIMIT)),
+        Command::PredictEval(eval_args) => {
+            predict_eval::predict_eval(&load(eval_args.history), eval_args)
+        }
+        Command::Dataset(dataset_args) => {
+            dataset::export(&load(dataset_args.history), dataset_args)
+                .expect("failed to export dataset")
+        }
+        Command::Snapshot(SnapshotArgs::Save { path, history }) => {
+            snapshot::save(&load(*history), path).expect("failed to save snapshot")
+        }
+        Command::Snapshot(SnapshotArgs::Load { path }) => {
+            snapshot::describe(&snapshot::load(path, u32::MAX).expect("failed to load snapshot"))
+        }
+        Command::Daemon(daemon_args) => {
+            daemon::run(&open_server_repo(), daemon_args, &config, &hooks)
+        }
     }
-    eprintln!("Done");
+    debug!("Done");
 }
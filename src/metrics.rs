@@ -0,0 +1,175 @@
+use crate::*;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// Everything we export about a single server tick
+#[derive(Debug)]
+pub struct TickMetrics {
+    pub squares_by_team: BTreeMap<Team, usize>,
+    pub our_team: Team,
+    pub move_changed: bool,
+    pub decision_time: Duration,
+    pub load_time: Duration,
+    pub tick_duration: Option<Duration>,
+    pub load_warnings: usize,
+    pub last_server_commit_time: i64,
+}
+
+fn team_label(team: Team) -> String {
+    format!("{:?}", team).to_lowercase()
+}
+
+fn write_metric(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    kind: &str,
+    value: impl std::fmt::Display,
+) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+    writeln!(out, "{} {}", name, value).unwrap();
+}
+
+impl TickMetrics {
+    pub fn new(game: &Game) -> Self {
+        let mut squares_by_team = BTreeMap::new();
+        for square in game.map().squares.iter().flatten() {
+            *squares_by_team.entry(square.controlled_by).or_insert(0) += 1;
+        }
+        Self {
+            squares_by_team,
+            our_team: game.our_team(),
+            move_changed: false,
+            decision_time: Duration::default(),
+            load_time: Duration::default(),
+            tick_duration: None,
+            load_warnings: game.load_warnings.len(),
+            last_server_commit_time: 0,
+        }
+    }
+
+    /// Renders in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        writeln!(
+            out,
+            "# HELP gitland_squares Squares controlled by each team"
+        )
+        .unwrap();
+        writeln!(out, "# TYPE gitland_squares gauge").unwrap();
+        for (team, count) in &self.squares_by_team {
+            writeln!(
+                out,
+                "gitland_squares{{team=\"{}\"}} {}",
+                team_label(*team),
+                count
+            )
+            .unwrap();
+        }
+        let total: usize = self.squares_by_team.values().sum();
+        let ours = self
+            .squares_by_team
+            .get(&self.our_team)
+            .copied()
+            .unwrap_or(0);
+        let share = if total > 0 {
+            ours as f64 / total as f64
+        } else {
+            0.0
+        };
+        write_metric(
+            &mut out,
+            "gitland_our_square_share",
+            "Fraction of the map controlled by our team",
+            "gauge",
+            share,
+        );
+        write_metric(
+            &mut out,
+            "gitland_move_changed",
+            "1 if we changed our move this tick",
+            "gauge",
+            self.move_changed as u8,
+        );
+        write_metric(
+            &mut out,
+            "gitland_decision_seconds",
+            "How long the AI took to decide",
+            "gauge",
+            self.decision_time.as_secs_f64(),
+        );
+        write_metric(
+            &mut out,
+            "gitland_server_load_seconds",
+            "How long loading the game from the server repo took",
+            "gauge",
+            self.load_time.as_secs_f64(),
+        );
+        if let Some(tick_duration) = self.tick_duration {
+            write_metric(
+                &mut out,
+                "gitland_server_tick_seconds",
+                "Time between the last two server commits we saw",
+                "gauge",
+                tick_duration.as_secs_f64(),
+            );
+        }
+        write_metric(
+            &mut out,
+            "gitland_parse_warnings",
+            "Problems skipped over while loading the game",
+            "gauge",
+            self.load_warnings,
+        );
+        write_metric(
+            &mut out,
+            "gitland_last_server_commit_timestamp_seconds",
+            "When the server commit we acted on was made",
+            "gauge",
+            self.last_server_commit_time,
+        );
+        out
+    }
+
+    /// Writes to a temporary file and renames it, so the collector never sees a partial file
+    pub fn write_textfile(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let tmp_path = path.with_extension("prom.tmp");
+        fs::write(&tmp_path, self.render())?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let mut squares_by_team = BTreeMap::new();
+        squares_by_team.insert(Team::Null, 2);
+        squares_by_team.insert(Team::Red, 6);
+        let metrics = TickMetrics {
+            squares_by_team,
+            our_team: Team::Red,
+            move_changed: true,
+            decision_time: Duration::from_millis(5),
+            load_time: Duration::from_millis(20),
+            tick_duration: None,
+            load_warnings: 1,
+            last_server_commit_time: 1600000000,
+        };
+        let text = metrics.render();
+        assert!(text.contains("gitland_squares{team=\"red\"} 6\n"));
+        assert!(text.contains("gitland_squares{team=\"null\"} 2\n"));
+        assert!(text.contains("gitland_our_square_share 0.75\n"));
+        assert!(text.contains("gitland_move_changed 1\n"));
+        assert!(text.contains("gitland_parse_warnings 1\n"));
+        assert!(text.contains("gitland_last_server_commit_timestamp_seconds 1600000000\n"));
+        assert!(!text.contains("gitland_server_tick_seconds"));
+    }
+}
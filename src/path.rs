@@ -0,0 +1,156 @@
+use crate::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Cost of stepping onto a square when no team weighting is used, and the lowest cost of any step
+const BASE_COST: u32 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    /// The direction to move in to take the first step of the path (Null if already at the goal)
+    pub first: Direction,
+    /// Every square from the start to the goal, inclusive
+    pub squares: Vec<Position>,
+    pub cost: u32,
+}
+
+/// How much it costs to step onto a square. When painting for a team, squares the team already
+/// controls cost more so paths prefer to paint over enemy and empty squares on the way.
+fn step_cost(map: &Map, pos: Position, painting_for: Option<Team>) -> u32 {
+    match painting_for {
+        None => BASE_COST,
+        Some(team) => match map.controller_of(pos) {
+            controller if controller == team => BASE_COST + 5,
+            Team::Null => BASE_COST + 2,
+            _ => BASE_COST,
+        },
+    }
+}
+
+fn is_blocked(map: &Map, pos: Position) -> bool {
+    match map.square(pos) {
+        Some(square) => square.occupied_by_player.is_some(),
+        None => true,
+    }
+}
+
+/// A* search from start to goal. Squares occupied by players (other than at the start) can not be
+/// walked through. Returns None if the goal can not be reached.
+pub fn find_path(
+    map: &Map,
+    start: Position,
+    goal: Position,
+    painting_for: Option<Team>,
+) -> Option<Path> {
+    if !map.is_inside(start) || (start != goal && is_blocked(map, goal)) {
+        return None;
+    }
+    let heuristic = |pos: Position| pos.distance(goal) as u32 * BASE_COST;
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<Position, Position> = HashMap::new();
+    let mut best_cost: HashMap<Position, u32> = HashMap::new();
+    best_cost.insert(start, 0);
+    open.push(Reverse((heuristic(start), 0, start.x, start.y)));
+    while let Some(Reverse((_, cost, x, y))) = open.pop() {
+        let pos = Position::new(x, y);
+        if pos == goal {
+            let mut squares = vec![pos];
+            let mut current = pos;
+            while let Some(previous) = came_from.get(&current) {
+                squares.push(*previous);
+                current = *previous;
+            }
+            squares.reverse();
+            let first = match squares.get(1) {
+                Some(next) => Direction::MOVES
+                    .iter()
+                    .copied()
+                    .find(|direction| start.step(*direction) == *next)
+                    .expect("path step is not adjacent"),
+                None => Direction::Null,
+            };
+            return Some(Path {
+                first,
+                squares,
+                cost,
+            });
+        }
+        if cost > best_cost[&pos] {
+            continue;
+        }
+        for next in map.neighbors(pos) {
+            if is_blocked(map, next) {
+                continue;
+            }
+            let next_cost = cost + step_cost(map, next, painting_for);
+            if best_cost.get(&next).is_none_or(|&known| next_cost < known) {
+                best_cost.insert(next, next_cost);
+                came_from.insert(next, pos);
+                open.push(Reverse((
+                    next_cost + heuristic(next),
+                    next_cost,
+                    next.x,
+                    next.y,
+                )));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures::map;
+
+    #[test]
+    fn straight_line() {
+        let map = map(&["....", "....", "...."], &[("us", 0, 1)]);
+        let path = find_path(&map, Position::new(0, 1), Position::new(3, 1), None).unwrap();
+        assert_eq!(path.first, Direction::Right);
+        assert_eq!(path.squares.len(), 4);
+        assert_eq!(path.cost, 3 * BASE_COST);
+    }
+
+    #[test]
+    fn goes_around_players() {
+        let map = map(&["....", "....", "...."], &[("us", 0, 1), ("them", 1, 1)]);
+        let path = find_path(&map, Position::new(0, 1), Position::new(2, 1), None).unwrap();
+        assert_ne!(path.first, Direction::Right);
+        assert_eq!(path.squares.len(), 5);
+        assert!(!path.squares.contains(&Position::new(1, 1)));
+    }
+
+    #[test]
+    fn unreachable() {
+        let map = map(
+            &["...", "...", "..."],
+            &[("us", 0, 0), ("a", 1, 0), ("b", 0, 1)],
+        );
+        assert_eq!(
+            find_path(&map, Position::new(0, 0), Position::new(2, 2), None),
+            None
+        );
+    }
+
+    #[test]
+    fn already_there() {
+        let map = map(&["..."], &[("us", 1, 0)]);
+        let path = find_path(&map, Position::new(1, 0), Position::new(1, 0), None).unwrap();
+        assert_eq!(path.first, Direction::Null);
+        assert_eq!(path.squares, vec![Position::new(1, 0)]);
+    }
+
+    #[test]
+    fn prefers_painting_enemy_squares() {
+        let map = map(&["rrr", "ggg", "..."], &[("us", 0, 0)]);
+        let path = find_path(
+            &map,
+            Position::new(0, 0),
+            Position::new(2, 1),
+            Some(Team::Red),
+        )
+        .unwrap();
+        assert_eq!(path.first, Direction::Down);
+    }
+}
@@ -0,0 +1,301 @@
+//! Predicting where other players will move next, from statistics learned on server history
+
+use crate::*;
+use std::collections::HashMap;
+
+/// What stepping in a direction would land a player on
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+enum Landing {
+    Stay,
+    Own,
+    Empty,
+    Enemy,
+    Blocked,
+}
+
+/// How a possible move relates to the player's situation. The predictor learns how often each kind
+/// of move is taken when it is available.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+struct Feature {
+    landing: Landing,
+    continues_straight: bool,
+    reverses: bool,
+}
+
+fn opposite(direction: Direction) -> Direction {
+    match direction {
+        Direction::Null => Direction::Null,
+        Direction::Up => Direction::Down,
+        Direction::Down => Direction::Up,
+        Direction::Left => Direction::Right,
+        Direction::Right => Direction::Left,
+    }
+}
+
+/// The direction that gets from one position to the other in a single move, if there is one
+pub fn direction_between(from: Position, to: Position) -> Option<Direction> {
+    Direction::ALL
+        .iter()
+        .copied()
+        .find(|direction| from.step(*direction) == to)
+}
+
+fn feature(
+    map: &Map,
+    pos: Position,
+    team: Team,
+    previous: Option<Direction>,
+    direction: Direction,
+) -> Feature {
+    let landing = if direction == Direction::Null {
+        Landing::Stay
+    } else {
+        match map.square(pos.step(direction)) {
+            None => Landing::Blocked,
+            Some(square) if square.occupied_by_player.is_some() => Landing::Blocked,
+            Some(square) if square.controlled_by == team => Landing::Own,
+            Some(square) if square.controlled_by == Team::Null => Landing::Empty,
+            Some(_) => Landing::Enemy,
+        }
+    };
+    let moving = previous.filter(|previous| *previous != Direction::Null);
+    Feature {
+        landing,
+        continues_straight: direction != Direction::Null && moving == Some(direction),
+        reverses: direction != Direction::Null && moving.map(opposite) == Some(direction),
+    }
+}
+
+/// One move a player made between two maps of the timeline
+#[derive(Debug)]
+pub struct Observation<'a> {
+    /// Index into the timeline of the map the player moved from
+    pub tick: usize,
+    pub name: &'a str,
+    pub team: Team,
+    /// The map the player moved from
+    pub map: &'a Map,
+    pub previous: Option<Direction>,
+    pub actual: Direction,
+}
+
+/// Every move in the timeline where the player was present for the two maps before and the one
+/// after. Jumps of more than one square (ex respawns) are skipped.
+pub fn observations(game: &Game) -> Vec<Observation<'_>> {
+    let mut result = Vec::new();
+    for (i, map) in game.timeline.iter().enumerate().skip(1) {
+        let (before, after) = (&game.timeline[i - 1], game.timeline.get(i + 1));
+        let after = match after {
+            Some(after) => after,
+            None => break,
+        };
+        let mut names: Vec<&String> = map.players.keys().collect();
+        names.sort();
+        for name in names {
+            let pos = map.players[name];
+            let team = match game.players.get(name) {
+                Some(player) => player.team,
+                None => continue,
+            };
+            let previous = before
+                .players
+                .get(name)
+                .and_then(|before_pos| direction_between(*before_pos, pos));
+            let actual = match after
+                .players
+                .get(name)
+                .and_then(|after_pos| direction_between(pos, *after_pos))
+            {
+                Some(actual) => actual,
+                None => continue,
+            };
+            result.push(Observation {
+                tick: i,
+                name,
+                team,
+                map,
+                previous,
+                actual,
+            });
+        }
+    }
+    result
+}
+
+#[derive(Debug, Default)]
+pub struct MovePredictor {
+    /// How many times a move with each feature was available
+    available: HashMap<Feature, u32>,
+    /// How many times a move with each feature was taken
+    chosen: HashMap<Feature, u32>,
+}
+
+impl MovePredictor {
+    pub fn train(&mut self, observations: &[Observation]) {
+        for observation in observations {
+            let pos = observation.map.players[observation.name];
+            for direction in &Direction::ALL {
+                let feature = feature(
+                    observation.map,
+                    pos,
+                    observation.team,
+                    observation.previous,
+                    *direction,
+                );
+                *self.available.entry(feature).or_insert(0) += 1;
+                if *direction == observation.actual {
+                    *self.chosen.entry(feature).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    /// How likely a move of this kind is to be taken, smoothed so unseen features are not ruled out
+    fn weight(&self, feature: Feature) -> f64 {
+        if feature.landing == Landing::Blocked {
+            // Moving into a wall or player is the same as staying, so assume nobody chooses it
+            return 0.0;
+        }
+        let available = self.available.get(&feature).copied().unwrap_or(0) as f64;
+        let chosen = self.chosen.get(&feature).copied().unwrap_or(0) as f64;
+        (chosen + 1.0) / (available + Direction::ALL.len() as f64)
+    }
+
+    /// Probability of each direction for a player currently at pos on map
+    pub fn predict(
+        &self,
+        map: &Map,
+        pos: Position,
+        team: Team,
+        previous: Option<Direction>,
+    ) -> Vec<(Direction, f64)> {
+        let weights: Vec<(Direction, f64)> = Direction::ALL
+            .iter()
+            .map(|direction| {
+                let feature = feature(map, pos, team, previous, *direction);
+                (*direction, self.weight(feature))
+            })
+            .collect();
+        let total: f64 = weights.iter().map(|(_, weight)| weight).sum();
+        weights
+            .into_iter()
+            .map(|(direction, weight)| (direction, weight / total))
+            .collect()
+    }
+
+    pub fn predict_observation(&self, observation: &Observation) -> Vec<(Direction, f64)> {
+        let pos = observation.map.players[observation.name];
+        self.predict(observation.map, pos, observation.team, observation.previous)
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Evaluation {
+    pub count: usize,
+    /// Fraction of moves where the most likely direction was right
+    pub accuracy: f64,
+    /// Mean natural log of the probability given to the actual move
+    pub mean_log_likelihood: f64,
+}
+
+pub fn evaluate(predictor: &MovePredictor, observations: &[Observation]) -> Evaluation {
+    if observations.is_empty() {
+        return Evaluation::default();
+    }
+    let mut correct = 0;
+    let mut log_likelihood = 0.0;
+    for observation in observations {
+        let prediction = predictor.predict_observation(observation);
+        let best = prediction
+            .iter()
+            .fold((Direction::Null, -1.0), |best, current| {
+                if current.1 > best.1 {
+                    *current
+                } else {
+                    best
+                }
+            });
+        if best.0 == observation.actual {
+            correct += 1;
+        }
+        let p = prediction
+            .iter()
+            .find(|(direction, _)| *direction == observation.actual)
+            .map(|(_, p)| *p)
+            .unwrap_or(0.0);
+        log_likelihood += p.max(1e-9).ln();
+    }
+    Evaluation {
+        count: observations.len(),
+        accuracy: correct as f64 / observations.len() as f64,
+        mean_log_likelihood: log_likelihood / observations.len() as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures::map;
+
+    fn map_with(pos: Position) -> Map {
+        map(&["....."; 5], &[("a", pos.x, pos.y)])
+    }
+
+    #[test]
+    fn learns_to_go_straight() {
+        let map = map_with(Position::new(2, 2));
+        let observations: Vec<Observation> = (0..20)
+            .map(|tick| Observation {
+                tick,
+                name: "a",
+                team: Team::Red,
+                map: &map,
+                previous: Some(Direction::Right),
+                actual: Direction::Right,
+            })
+            .collect();
+        let mut predictor = MovePredictor::default();
+        predictor.train(&observations);
+        let evaluation = evaluate(&predictor, &observations);
+        assert_eq!(evaluation.count, 20);
+        assert_eq!(evaluation.accuracy, 1.0);
+        let prediction =
+            predictor.predict(&map, Position::new(2, 2), Team::Red, Some(Direction::Up));
+        let up = prediction
+            .iter()
+            .find(|(d, _)| *d == Direction::Up)
+            .unwrap()
+            .1;
+        let down = prediction
+            .iter()
+            .find(|(d, _)| *d == Direction::Down)
+            .unwrap()
+            .1;
+        assert!(up > down);
+    }
+
+    #[test]
+    fn never_predicts_walls() {
+        let map = map_with(Position::new(0, 0));
+        let prediction =
+            MovePredictor::default().predict(&map, Position::new(0, 0), Team::Red, None);
+        let total: f64 = prediction.iter().map(|(_, p)| p).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        for (direction, p) in prediction {
+            if direction == Direction::Up || direction == Direction::Left {
+                assert_eq!(p, 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn direction_between_adjacent() {
+        let a = Position::new(3, 3);
+        assert_eq!(
+            direction_between(a, Position::new(3, 2)),
+            Some(Direction::Up)
+        );
+        assert_eq!(direction_between(a, a), Some(Direction::Null));
+        assert_eq!(direction_between(a, Position::new(5, 3)), None);
+    }
+}
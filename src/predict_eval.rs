@@ -0,0 +1,47 @@
+use crate::predict::{evaluate, observations, Evaluation, MovePredictor};
+use crate::*;
+
+#[derive(Debug, PartialEq)]
+pub struct PredictEvalArgs {
+    pub history: u32,
+    pub train_fraction: f64,
+}
+
+fn show_evaluation(name: &str, evaluation: &Evaluation) {
+    println!(
+        "{}: {:.1}% of {} moves right, mean log likelihood {:.3}",
+        name,
+        evaluation.accuracy * 100.0,
+        evaluation.count,
+        evaluation.mean_log_likelihood
+    );
+}
+
+pub fn predict_eval(game: &Game, args: &PredictEvalArgs) {
+    let our_team = game
+        .us
+        .as_ref()
+        .and_then(|us| game.players.get(us))
+        .map(|player| player.team);
+    let all = observations(game);
+    let (train, test): (Vec<_>, Vec<_>) = all
+        .into_iter()
+        .filter(|observation| Some(observation.team) != our_team)
+        .partition(|observation| {
+            (observation.tick as f64) < game.timeline.len() as f64 * args.train_fraction
+        });
+    println!(
+        "{} maps of history, training on {} moves and testing on {}",
+        game.timeline.len(),
+        train.len(),
+        test.len()
+    );
+    if test.is_empty() {
+        println!("Nothing to test on, try more history or a smaller training fraction");
+        return;
+    }
+    let mut predictor = MovePredictor::default();
+    predictor.train(&train);
+    show_evaluation("Untrained", &evaluate(&MovePredictor::default(), &test));
+    show_evaluation("Trained", &evaluate(&predictor, &test));
+}
@@ -0,0 +1,73 @@
+//! Running shell commands from the config without letting them outlive their welcome
+
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often wait_timeout checks whether the child has exited
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A `sh -c` command in its own process group, so kill takes down everything it started too
+pub fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command).process_group(0);
+    shell
+}
+
+/// Kills a child started with shell and everything it started, then reaps it
+pub fn kill(child: &mut Child) {
+    // The child leads its own process group, so the group ID is its PID
+    unsafe {
+        libc::killpg(child.id() as libc::pid_t, libc::SIGKILL);
+    }
+    let _ = child.wait();
+}
+
+/// Waits for a child started with shell to exit. If it takes longer than timeout it is killed and
+/// None is returned.
+pub fn wait_timeout(child: &mut Child, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            kill(child);
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::process::Stdio;
+
+    #[test]
+    fn timeout_kills_the_whole_group() {
+        let mut child = shell("sleep 5; echo done")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let start = Instant::now();
+        let status = wait_timeout(&mut child, Duration::from_millis(100)).unwrap();
+        assert!(status.is_none());
+        // Only reaches the end once sleep is dead too, since it holds the pipe open
+        let mut output = String::new();
+        child
+            .stdout
+            .take()
+            .unwrap()
+            .read_to_string(&mut output)
+            .unwrap();
+        assert_eq!(output, "");
+        assert!(start.elapsed() < Duration::from_secs(2));
+        let mut child = shell("exit 3").spawn().unwrap();
+        let status = wait_timeout(&mut child, Duration::from_secs(5)).unwrap();
+        assert_eq!(status.and_then(|status| status.code()), Some(3));
+    }
+}
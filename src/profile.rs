@@ -0,0 +1,239 @@
+//! Guessing what each player is up to from how they have moved
+
+use crate::*;
+
+/// How close another player has to stay to count as being followed
+const FOLLOW_DISTANCE: i32 = 2;
+/// Number of ticks of history needed for a full confidence score
+const CONFIDENT_TICKS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Behavior {
+    /// Not enough history to tell
+    Unknown,
+    /// Stays in one place
+    Idle,
+    /// Walks along the edges of the map
+    EdgePainter,
+    /// Walks onto squares other teams control
+    Raider,
+    /// Changes direction most ticks
+    RandomWalker,
+    /// Stays close to another player
+    Follower,
+}
+
+impl std::fmt::Display for Behavior {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unknown => write!(f, "unknown"),
+            Self::Idle => write!(f, "idle"),
+            Self::EdgePainter => write!(f, "edge painter"),
+            Self::Raider => write!(f, "raider"),
+            Self::RandomWalker => write!(f, "random walker"),
+            Self::Follower => write!(f, "follower"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub team: Team,
+    /// The behavior with the highest score
+    pub behavior: Behavior,
+    /// From 0 to 1, lower when there is little history
+    pub confidence: f32,
+    /// How well the player matches every behavior, from 0 to 1
+    pub scores: Vec<(Behavior, f32)>,
+    /// Number of ticks the player was on the map both before and after
+    pub ticks: usize,
+}
+
+fn is_on_edge(map: &Map, pos: Position) -> bool {
+    let width = map.width() as i32;
+    let height = map.height() as i32;
+    pos.x == 0 || pos.y == 0 || pos.x == width - 1 || pos.y == height - 1
+}
+
+fn profile_player(game: &Game, name: &str, team: Team) -> Profile {
+    let mut ticks = 0;
+    let mut moves = 0;
+    let mut edge_moves = 0;
+    let mut raids = 0;
+    let mut turns = 0;
+    let mut follows = 0;
+    let mut last_vector = None;
+    let mut last_followed: Option<&String> = None;
+    for pair in game.timeline.windows(2) {
+        let (before, after) = (&pair[0], &pair[1]);
+        let (from, to) = match (before.players.get(name), after.players.get(name)) {
+            (Some(from), Some(to)) => (*from, *to),
+            _ => {
+                last_vector = None;
+                last_followed = None;
+                continue;
+            }
+        };
+        ticks += 1;
+        let followed = after
+            .players
+            .iter()
+            .filter(|(other, pos)| *other != name && pos.distance(to) <= FOLLOW_DISTANCE)
+            .min_by_key(|(other, pos)| (pos.distance(to), *other))
+            .map(|(other, _)| other);
+        if followed.is_some() && followed == last_followed {
+            follows += 1;
+        }
+        last_followed = followed;
+        if from == to {
+            last_vector = None;
+            continue;
+        }
+        moves += 1;
+        if is_on_edge(after, to) {
+            edge_moves += 1;
+        }
+        let controller = before.controller_of(to);
+        if controller != team && controller != Team::Null {
+            raids += 1;
+        }
+        let vector = to - from;
+        if last_vector.is_some_and(|last| last != vector) {
+            turns += 1;
+        }
+        last_vector = Some(vector);
+    }
+    let fraction = |count: usize, total: usize| {
+        if total == 0 {
+            0.0
+        } else {
+            count as f32 / total as f32
+        }
+    };
+    let moving = fraction(moves, ticks);
+    // Without history there is nothing to say the player is idle either
+    let idle = if ticks == 0 { 0.0 } else { 1.0 - moving };
+    let scores = vec![
+        (Behavior::Idle, idle),
+        (Behavior::EdgePainter, fraction(edge_moves, moves) * moving),
+        (Behavior::Raider, fraction(raids, moves) * moving),
+        (Behavior::RandomWalker, fraction(turns, moves) * moving),
+        (Behavior::Follower, fraction(follows, ticks) * moving),
+    ];
+    let (behavior, best) = if ticks == 0 {
+        (Behavior::Unknown, 0.0)
+    } else {
+        // Earlier behaviors win ties
+        scores
+            .iter()
+            .fold((Behavior::Unknown, 0.0), |best, current| {
+                if current.1 > best.1 {
+                    *current
+                } else {
+                    best
+                }
+            })
+    };
+    Profile {
+        name: name.to_owned(),
+        team,
+        behavior,
+        confidence: best * fraction(ticks.min(CONFIDENT_TICKS), CONFIDENT_TICKS),
+        scores,
+        ticks,
+    }
+}
+
+/// Profiles every player in the game, sorted by name
+pub fn profile_players(game: &Game) -> Vec<Profile> {
+    let mut names: Vec<&String> = game.players.keys().collect();
+    names.sort();
+    names
+        .into_iter()
+        .map(|name| profile_player(game, name, game.players[name].team))
+        .collect()
+}
+
+pub fn show_players(game: &Game) {
+    let map = game.map();
+    for profile in profile_players(game) {
+        let mut position = match map.players.get(&profile.name) {
+            Some(pos) => pos.to_string(),
+            None => "not on map".to_owned(),
+        };
+        if game.players[&profile.name].inactive {
+            position += " (inactive)";
+        }
+        println!(
+            "{} ({:?}) at {}: {} ({:.0}% confident over {} ticks)",
+            profile.name,
+            profile.team,
+            position,
+            profile.behavior,
+            profile.confidence * 100.0,
+            profile.ticks
+        );
+        let scores: Vec<String> = profile
+            .scores
+            .iter()
+            .map(|(behavior, score)| format!("{} {:.2}", behavior, score))
+            .collect();
+        println!("  {}", scores.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures;
+
+    /// A player's name, team and position each tick
+    type Track<'a> = (&'a str, Team, &'a [(i32, i32)]);
+
+    fn game(tracks: &[Track]) -> Game {
+        let rows = ["......", "......", "..bb..", "......", "......", "......"];
+        let ticks = tracks[0].2.len();
+        let timeline = (0..ticks)
+            .map(|tick| {
+                let players: Vec<(&str, i32, i32)> = tracks
+                    .iter()
+                    .map(|(name, _, track)| (*name, track[tick].0, track[tick].1))
+                    .collect();
+                fixtures::map(&rows, &players)
+            })
+            .collect();
+        let teams: Vec<(&str, Team)> = tracks
+            .iter()
+            .map(|(name, team, _)| (*name, *team))
+            .collect();
+        fixtures::game(&teams, timeline)
+    }
+
+    #[test]
+    fn behaviors() {
+        let game = game(&[
+            ("edge", Team::Red, &[(0, 0), (1, 0), (2, 0), (3, 0), (4, 0)]),
+            ("idle", Team::Red, &[(5, 5), (5, 5), (5, 5), (5, 5), (5, 5)]),
+            ("raid", Team::Red, &[(2, 1), (2, 2), (3, 2), (3, 3), (3, 2)]),
+        ]);
+        let profiles = profile_players(&game);
+        let behaviors: Vec<Behavior> = profiles.iter().map(|p| p.behavior).collect();
+        assert_eq!(
+            behaviors,
+            vec![Behavior::EdgePainter, Behavior::Idle, Behavior::Raider]
+        );
+        assert_eq!(profiles[1].confidence, 0.4);
+        let raid_score = profiles[2]
+            .scores
+            .iter()
+            .find(|(behavior, _)| *behavior == Behavior::Raider)
+            .unwrap()
+            .1;
+        assert_eq!(raid_score, 0.75);
+        let new_player = self::game(&[("new", Team::Red, &[(0, 0)])]);
+        let profile = &profile_players(&new_player)[0];
+        assert_eq!(profile.behavior, Behavior::Unknown);
+        assert!(profile.scores.iter().all(|(_, score)| *score == 0.0));
+    }
+}
@@ -0,0 +1,114 @@
+//! Connected regions of squares controlled by the same team
+
+use crate::*;
+use std::collections::{BTreeSet, HashSet};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub team: Team,
+    pub squares: Vec<Position>,
+    /// Top left corner of the bounding box
+    pub min: Position,
+    /// Bottom right corner of the bounding box (inclusive)
+    pub max: Position,
+    /// Number of square edges that border something outside the region (including the map edge)
+    pub perimeter: usize,
+    pub players_inside: Vec<String>,
+    /// Players not in the region but on a square next to it
+    pub players_adjacent: Vec<String>,
+}
+
+impl Region {
+    pub fn size(&self) -> usize {
+        self.squares.len()
+    }
+}
+
+fn flood_fill(map: &Map, start: Position, seen: &mut HashSet<Position>) -> Region {
+    let team = map.controller_of(start);
+    let mut squares = Vec::new();
+    let mut stack = vec![start];
+    seen.insert(start);
+    while let Some(pos) = stack.pop() {
+        squares.push(pos);
+        for next in map.neighbors(pos) {
+            if map.controller_of(next) == team && seen.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    squares.sort_by_key(|pos| (pos.y, pos.x));
+    let members: HashSet<Position> = squares.iter().copied().collect();
+    let mut perimeter = 0;
+    let mut players_inside = BTreeSet::new();
+    let mut players_adjacent = BTreeSet::new();
+    for pos in &squares {
+        if let Some(name) = map.square(*pos).and_then(|s| s.occupied_by_player.as_ref()) {
+            players_inside.insert(name.clone());
+        }
+        // Edges against the side of the map count too, so this can not use Map::neighbors
+        for direction in &Direction::MOVES {
+            let next = pos.step(*direction);
+            if members.contains(&next) {
+                continue;
+            }
+            perimeter += 1;
+            if let Some(name) = map.square(next).and_then(|s| s.occupied_by_player.as_ref()) {
+                players_adjacent.insert(name.clone());
+            }
+        }
+    }
+    let min = Position::new(
+        squares.iter().map(|pos| pos.x).min().unwrap_or(0),
+        squares.iter().map(|pos| pos.y).min().unwrap_or(0),
+    );
+    let max = Position::new(
+        squares.iter().map(|pos| pos.x).max().unwrap_or(0),
+        squares.iter().map(|pos| pos.y).max().unwrap_or(0),
+    );
+    Region {
+        team,
+        squares,
+        min,
+        max,
+        perimeter,
+        players_inside: players_inside.into_iter().collect(),
+        players_adjacent: players_adjacent.into_iter().collect(),
+    }
+}
+
+/// Every 4-connected region on the map (including unclaimed ones), largest first
+pub fn find_regions(map: &Map) -> Vec<Region> {
+    let mut seen = HashSet::new();
+    let mut regions = Vec::new();
+    for pos in map.positions() {
+        if !seen.contains(&pos) {
+            regions.push(flood_fill(map, pos, &mut seen));
+        }
+    }
+    regions.sort_by_key(|region| std::cmp::Reverse(region.size()));
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures::map;
+
+    #[test]
+    fn separate_regions() {
+        let map = map(&["rr.g", "rr.g", "...r"], &[("a", 0, 0), ("b", 2, 1)]);
+        let regions = find_regions(&map);
+        let red: Vec<&Region> = regions.iter().filter(|r| r.team == Team::Red).collect();
+        assert_eq!(red.len(), 2);
+        assert_eq!(red[0].size(), 4);
+        assert_eq!(red[0].min, Position::new(0, 0));
+        assert_eq!(red[0].max, Position::new(1, 1));
+        assert_eq!(red[0].perimeter, 8);
+        assert_eq!(red[0].players_inside, vec!["a".to_owned()]);
+        assert_eq!(red[0].players_adjacent, vec!["b".to_owned()]);
+        assert_eq!(red[1].size(), 1);
+        let total: usize = regions.iter().map(Region::size).sum();
+        assert_eq!(total, 12);
+    }
+}
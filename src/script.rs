@@ -0,0 +1,188 @@
+//! Strategies written in Rhai (https://rhai.rs). The script is read again every tick, so edits take
+//! effect on the next move without restarting.
+//!
+//! Scripts can use:
+//! - `map`: `map.width`, `map.height`, `map.is_inside(pos)`, `map.controller_of(pos)` (a team
+//!   name) and `map.square(pos)` (`()` off the map, otherwise has `controlled_by` and
+//!   `occupied_by`, which is `()` if nobody is there)
+//! - `players`: an array of `#{ name, team, position, inactive }`
+//! - `us`, `our_team` and `our_position`
+//! - `pos(x, y)`, and `x`, `y`, `distance(other)`, `step(direction)` and `+` on positions
+//! - `IDLE`, `UP`, `DOWN`, `LEFT` and `RIGHT`
+//!
+//! The value of the last expression is the move, either one of the constants or its name as a
+//! string. Team names are `Null`, `Red`, `Green` and `Blue`.
+
+use crate::*;
+use log::{debug, info};
+use rhai::{Array, Dynamic, Engine, Scope};
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+
+/// Stops scripts that loop forever
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptConfig {
+    pub path: PathBuf,
+}
+
+fn team_name(team: Team) -> String {
+    format!("{:?}", team)
+}
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine
+        .register_type_with_name::<Position>("Position")
+        .register_fn("pos", |x: i64, y: i64| Position::new(x as i32, y as i32))
+        .register_get("x", |pos: &mut Position| pos.x as i64)
+        .register_get("y", |pos: &mut Position| pos.y as i64)
+        .register_fn("distance", |a: &mut Position, b: Position| {
+            a.distance(b) as i64
+        })
+        .register_fn("step", |pos: &mut Position, direction: Direction| {
+            pos.step(direction)
+        })
+        .register_fn("+", |a: Position, b: Position| a + b)
+        .register_fn("==", |a: Position, b: Position| a == b)
+        .register_fn("!=", |a: Position, b: Position| a != b)
+        .register_fn("to_string", |pos: &mut Position| pos.to_string());
+    engine
+        .register_type_with_name::<Direction>("Direction")
+        .register_fn("==", |a: Direction, b: Direction| a == b)
+        .register_fn("!=", |a: Direction, b: Direction| a != b)
+        .register_fn("to_string", |direction: &mut Direction| {
+            direction.to_string()
+        });
+    engine
+        .register_type_with_name::<Square>("Square")
+        .register_get("controlled_by", |square: &mut Square| {
+            team_name(square.controlled_by)
+        })
+        .register_get("occupied_by", |square: &mut Square| {
+            match &square.occupied_by_player {
+                Some(name) => Dynamic::from(name.clone()),
+                None => Dynamic::UNIT,
+            }
+        });
+    engine
+        .register_type_with_name::<Map>("Map")
+        .register_get("width", |map: &mut Map| map.width() as i64)
+        .register_get("height", |map: &mut Map| map.height() as i64)
+        .register_fn("is_inside", |map: &mut Map, pos: Position| {
+            map.is_inside(pos)
+        })
+        .register_fn("controller_of", |map: &mut Map, pos: Position| {
+            team_name(map.controller_of(pos))
+        })
+        .register_fn("square", |map: &mut Map, pos: Position| {
+            match map.square(pos) {
+                Some(square) => Dynamic::from(square.clone()),
+                None => Dynamic::UNIT,
+            }
+        });
+    engine
+}
+
+fn scope(game: &Game) -> Scope<'static> {
+    let map = game.map();
+    let mut names: Vec<&String> = map.players.keys().collect();
+    names.sort();
+    let players: Array = names
+        .into_iter()
+        .filter_map(|name| {
+            let player = game.players.get(name)?;
+            let mut object = rhai::Map::new();
+            object.insert("name".into(), Dynamic::from(name.clone()));
+            object.insert("team".into(), Dynamic::from(team_name(player.team)));
+            object.insert("position".into(), Dynamic::from(map.players[name]));
+            object.insert("inactive".into(), Dynamic::from(player.inactive));
+            Some(Dynamic::from(object))
+        })
+        .collect();
+    let mut scope = Scope::new();
+    scope
+        .push_constant("map", map.clone())
+        .push_constant("players", players)
+        .push_constant("us", game.us.clone().expect("we do not have a player"))
+        .push_constant("our_team", team_name(game.our_team()))
+        .push_constant("our_position", game.our_position());
+    for direction in &Direction::ALL {
+        let name = match direction {
+            Direction::Null => "IDLE".to_owned(),
+            other => other.to_string().to_uppercase(),
+        };
+        scope.push_constant(name, *direction);
+    }
+    scope
+}
+
+/// Runs a script's source and returns the direction it picked
+pub fn run(game: &Game, source: &str) -> Result<Direction, Box<dyn Error>> {
+    let result: Dynamic = engine().eval_with_scope(&mut scope(game), source)?;
+    if result.is::<Direction>() {
+        Ok(result.cast())
+    } else if let Some(name) = result.clone().try_cast::<String>() {
+        Ok(name.parse()?)
+    } else {
+        Err(format!(
+            "script returned {} instead of a direction",
+            result.type_name()
+        )
+        .into())
+    }
+}
+
+pub fn decide(game: &Game, config: &ScriptConfig) -> Result<Direction, Box<dyn Error>> {
+    debug!("Loading script {}", config.path.display());
+    let source = fs::read_to_string(&config.path)
+        .map_err(|e| format!("failed to read {}: {}", config.path.display(), e))?;
+    let direction = run(game, &source)?;
+    info!("Script chose {}", direction);
+    Ok(direction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures;
+
+    fn game() -> Game {
+        let map = fixtures::map(&["b..", "..."], &[("us", 1, 0), ("them", 2, 1)]);
+        let mut game = fixtures::game(&[("us", Team::Red), ("them", Team::Blue)], vec![map]);
+        game.us = Some("us".to_owned());
+        game
+    }
+
+    #[test]
+    fn scripts_can_see_the_game() {
+        let game = game();
+        let script = r#"
+            let left = our_position.step(LEFT);
+            if map.controller_of(left) != our_team && map.square(left).occupied_by == () {
+                LEFT
+            } else {
+                IDLE
+            }
+        "#;
+        assert_eq!(run(&game, script).unwrap(), Direction::Left);
+        let script = r#"
+            let them = players.filter(|p| p.team != our_team)[0];
+            if our_position.distance(them.position) == 2 && map.width == 3 { "down" } else { "up" }
+        "#;
+        assert_eq!(run(&game, script).unwrap(), Direction::Down);
+    }
+
+    #[test]
+    fn bad_scripts_are_errors() {
+        let game = game();
+        assert!(run(&game, "42").is_err());
+        assert!(run(&game, "\"sideways\"").is_err());
+        assert!(run(&game, "loop {}").is_err());
+        assert!(run(&game, "map.square(pos(5, 5)).controlled_by").is_err());
+    }
+}
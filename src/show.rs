@@ -1,9 +1,38 @@
+use crate::config::Config;
+use crate::danger::DangerMap;
 use crate::*;
 use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Extra per-square data that can be drawn over the map
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overlay {
+    Danger,
+    Visits,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ShowArgs {
+    pub overlay: Option<Overlay>,
+    /// Print only the overlay, as CSV
+    pub csv: bool,
+}
+
+impl FromStr for Overlay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "danger" => Ok(Self::Danger),
+            "visits" => Ok(Self::Visits),
+            other => Err(format!("unknown overlay {:?}", other)),
+        }
+    }
+}
 
 fn show_player_stats(game: &Game) {
     let mut players_by_team = BTreeMap::new();
-    for player in game.players.values() {
+    for player in game.current_players() {
         if let Some(count) = players_by_team.get_mut(&player.team) {
             *count += 1;
         } else {
@@ -15,6 +44,15 @@ fn show_player_stats(game: &Game) {
     }
 }
 
+fn show_quarantine(map: &Map) {
+    for quarantined in &map.quarantine {
+        println!(
+            "{} left off the map: {}",
+            quarantined.name, quarantined.reason
+        );
+    }
+}
+
 fn show_map_stats(map: &Map) {
     let mut squares_by_team = BTreeMap::new();
     for square in map.squares.iter().flatten() {
@@ -24,7 +62,7 @@ fn show_map_stats(map: &Map) {
             squares_by_team.insert(square.controlled_by, 1);
         }
     }
-    let total_squares = map.squares.len() * map.squares[0].len();
+    let total_squares = map.width() * map.height();
     for (player, count) in squares_by_team {
         println!(
             "{} squares controlled by {:?} ({:.1}%)",
@@ -35,7 +73,121 @@ fn show_map_stats(map: &Map) {
     }
 }
 
-pub fn show(game: &Game) {
-    show_player_stats(game);
-    show_map_stats(game.timeline.last().expect("no maps"));
+const REGIONS_PER_TEAM: usize = 3;
+
+fn show_regions(map: &Map) {
+    let regions = region::find_regions(map);
+    let mut shown_by_team = BTreeMap::new();
+    for region in &regions {
+        if region.team == Team::Null {
+            continue;
+        }
+        let shown = shown_by_team.entry(region.team).or_insert(0);
+        if *shown == 0 {
+            let count = regions.iter().filter(|r| r.team == region.team).count();
+            println!("{:?} has {} regions, largest:", region.team, count);
+        }
+        if *shown < REGIONS_PER_TEAM {
+            println!(
+                "  {} squares from {} to {}, perimeter {}, inside: [{}], adjacent: [{}]",
+                region.size(),
+                region.min,
+                region.max,
+                region.perimeter,
+                region.players_inside.join(", "),
+                region.players_adjacent.join(", ")
+            );
+        }
+        *shown += 1;
+    }
+}
+
+pub fn show_frontier(game: &Game) {
+    let frontier = game.map().frontier();
+    for ((a, b), count) in &frontier.pair_counts {
+        println!("{:?}/{:?}: {} edges", a, b, count);
+    }
+    let mut squares_by_team = BTreeMap::new();
+    for square in &frontier.squares {
+        *squares_by_team.entry(square.team).or_insert(0) += 1;
+    }
+    for (team, count) in squares_by_team {
+        println!("{:?} has {} frontier squares", team, count);
+    }
+}
+
+/// Draws one character per square, with players drawn as @
+fn show_grid(map: &Map, square_char: impl Fn(Position) -> char) {
+    for y in 0..map.height() {
+        let line: String = (0..map.width())
+            .map(|x| Position::new(x as i32, y as i32))
+            .map(
+                |pos| match map.square(pos).and_then(|s| s.occupied_by_player.as_ref()) {
+                    Some(_) => '@',
+                    None => square_char(pos),
+                },
+            )
+            .collect();
+        println!("{}", line);
+    }
+}
+
+fn show_danger(game: &Game, config: &Config, csv: bool) {
+    let danger = DangerMap::new(game, game.our_team(), config.ai.danger_horizon);
+    if csv {
+        println!("x,y,risk");
+        for (y, row) in danger.risk.iter().enumerate() {
+            for (x, risk) in row.iter().enumerate() {
+                println!("{},{},{}", x, y, risk);
+            }
+        }
+        return;
+    }
+    println!(
+        "Risk of {:?} losing each square within {} ticks (0-9, @ is a player):",
+        danger.team, config.ai.danger_horizon
+    );
+    show_grid(game.map(), |pos| {
+        let digit = (danger.risk(pos) * 9.0).round() as u32;
+        std::char::from_digit(digit, 10).expect("risk out of range")
+    });
+}
+
+fn show_visits(game: &Game, csv: bool) {
+    let visits = game.visits();
+    if csv {
+        print!("{}", visits.to_csv());
+        return;
+    }
+    let team = game.our_team();
+    let most = game
+        .map()
+        .positions()
+        .map(|pos| visits.enemy_count(team, pos))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    println!(
+        "Enemy visits over {} ticks (. is none, 0-9 scaled to the busiest square with {}, @ is a player):",
+        visits.ticks, most
+    );
+    show_grid(game.map(), |pos| match visits.enemy_count(team, pos) {
+        0 => '.',
+        count => std::char::from_digit(count * 9 / most, 10).expect("visits out of range"),
+    });
+}
+
+/// With csv only the overlay is printed, as CSV
+pub fn show(game: &Game, args: &ShowArgs, config: &Config) {
+    if !args.csv {
+        show_player_stats(game);
+        show_quarantine(game.map());
+        show_map_stats(game.timeline.last().expect("no maps"));
+        show_regions(game.map());
+    }
+    match args.overlay {
+        Some(Overlay::Danger) => show_danger(game, config, args.csv),
+        Some(Overlay::Visits) => show_visits(game, args.csv),
+        None => (),
+    }
 }
@@ -0,0 +1,119 @@
+//! A local simulation of gitland's rules, for looking ahead
+
+use crate::*;
+use std::collections::HashMap;
+
+/// Moves a player one square and paints the square they land on. Returns false (and leaves the map
+/// alone) if the player isn't on the map or the move is off the map or into another player.
+pub fn apply_move(map: &mut Map, name: &str, team: Team, direction: Direction) -> bool {
+    let from = match map.players.get(name) {
+        Some(pos) => *pos,
+        None => return false,
+    };
+    let to = from.step(direction);
+    if to == from {
+        return true;
+    }
+    match map.square(to) {
+        Some(square) if square.occupied_by_player.is_none() => (),
+        _ => return false,
+    }
+    map.squares[from.y as usize][from.x as usize].occupied_by_player = None;
+    let square = &mut map.squares[to.y as usize][to.x as usize];
+    square.occupied_by_player = Some(name.to_owned());
+    square.controlled_by = team;
+    map.players.insert(name.to_owned(), to);
+    true
+}
+
+/// A simple guess at what a player will do: step onto a free neighboring square their team does
+/// not control yet, or stay put if there are none
+pub fn predict_move(map: &Map, name: &str, team: Team) -> Direction {
+    let pos = match map.players.get(name) {
+        Some(pos) => *pos,
+        None => return Direction::Null,
+    };
+    Direction::MOVES
+        .iter()
+        .copied()
+        .find(|direction| match map.square(pos.step(*direction)) {
+            Some(square) => square.occupied_by_player.is_none() && square.controlled_by != team,
+            None => false,
+        })
+        .unwrap_or(Direction::Null)
+}
+
+/// Advances the map by one tick. Players with no entry in moves follow predict_move, except inactive
+/// players who stay put. Players move one at a time in name order, so earlier players win
+/// collisions.
+pub fn step(
+    map: &Map,
+    players: &HashMap<String, Player>,
+    moves: &HashMap<String, Direction>,
+) -> Map {
+    let mut next = map.clone();
+    let mut names: Vec<&String> = map.players.keys().collect();
+    names.sort();
+    for name in names {
+        let player = match players.get(name) {
+            Some(player) => player,
+            None => continue,
+        };
+        let team = player.team;
+        let direction = match moves.get(name) {
+            Some(direction) => *direction,
+            None if player.inactive => Direction::Null,
+            None => predict_move(&next, name, team),
+        };
+        apply_move(&mut next, name, team, direction);
+    }
+    next
+}
+
+pub fn squares_controlled_by(map: &Map, team: Team) -> usize {
+    map.squares
+        .iter()
+        .flatten()
+        .filter(|square| square.controlled_by == team)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures;
+
+    fn game() -> (Map, HashMap<String, Player>) {
+        let map = fixtures::map(&["...", "...", "..."], &[("red", 0, 0), ("green", 2, 2)]);
+        let mut game = fixtures::game(&[("red", Team::Red), ("green", Team::Green)], vec![map]);
+        (game.timeline.remove(0), game.players)
+    }
+
+    #[test]
+    fn move_paints_square() {
+        let (mut map, _) = game();
+        assert!(apply_move(&mut map, "red", Team::Red, Direction::Right));
+        assert_eq!(map.players["red"], Position::new(1, 0));
+        assert_eq!(map.controller_of(Position::new(1, 0)), Team::Red);
+        assert!(map.squares[0][0].occupied_by_player.is_none());
+        assert_eq!(map.squares[0][1].occupied_by_player.as_deref(), Some("red"));
+    }
+
+    #[test]
+    fn can_not_leave_map() {
+        let (mut map, _) = game();
+        assert!(!apply_move(&mut map, "red", Team::Red, Direction::Up));
+        assert_eq!(map.players["red"], Position::new(0, 0));
+    }
+
+    #[test]
+    fn step_moves_everyone() {
+        let (map, players) = game();
+        let mut moves = HashMap::new();
+        moves.insert("red".to_owned(), Direction::Down);
+        let next = step(&map, &players, &moves);
+        assert_eq!(next.players["red"], Position::new(0, 1));
+        assert_ne!(next.players["green"], Position::new(2, 2));
+        assert_eq!(squares_controlled_by(&next, Team::Green), 1);
+    }
+}
@@ -0,0 +1,87 @@
+//! Saving a loaded game to a single JSON file and loading it back in place of the server repo, so a
+//! bug can be reproduced from one attachment
+
+use crate::*;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+#[derive(Debug, PartialEq)]
+pub enum SnapshotArgs {
+    Save { path: OsString, history: u32 },
+    Load { path: OsString },
+}
+
+pub fn save(game: &Game, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+    let path = path.as_ref();
+    let file =
+        File::create(path).map_err(|e| format!("failed to create {}: {}", path.display(), e))?;
+    let mut out = BufWriter::new(file);
+    serde_json::to_writer(&mut out, game)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Keeps only the newest history_limit maps of the snapshot's timeline
+pub fn load(path: impl AsRef<Path>, history_limit: u32) -> Result<Game, Box<dyn Error>> {
+    let path = path.as_ref();
+    let file = File::open(path).map_err(|e| format!("failed to open {}: {}", path.display(), e))?;
+    let mut game: Game = serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("invalid snapshot {}: {}", path.display(), e))?;
+    if game.timeline.is_empty() {
+        return Err(format!("snapshot {} has no maps", path.display()).into());
+    }
+    let excess = game.timeline.len().saturating_sub(history_limit as usize);
+    game.timeline.drain(..excess);
+    Ok(game)
+}
+
+/// Prints what a snapshot holds
+pub fn describe(game: &Game) {
+    let map = game.map();
+    println!(
+        "{} maps of a {}x{} map with {} players ({} on the map)",
+        game.timeline.len(),
+        map.width(),
+        map.height(),
+        game.players.len(),
+        map.players.len()
+    );
+    for warning in &game.load_warnings {
+        println!("Load warning: {}", warning);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures;
+
+    #[test]
+    fn round_trip() {
+        let map = |x: i32| fixtures::map(&["...", "..b"], &[("a", x, 0), ("b", 5, 5)]);
+        let mut game = fixtures::game(&[("a", Team::Blue)], vec![map(0), map(1), map(2)]);
+        game.us = Some("a".to_owned());
+        game.players.get_mut("a").unwrap().inactive = true;
+        game.load_warnings.push("something".to_owned());
+        let path = std::env::temp_dir().join(format!("snapshot-test-{}.json", std::process::id()));
+        save(&game, &path).unwrap();
+        let loaded = load(&path, 2).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            serde_json::to_string(&loaded.timeline).unwrap(),
+            serde_json::to_string(&game.timeline[1..]).unwrap()
+        );
+        let map = loaded.map();
+        assert_eq!((map.width(), map.height()), (3, 2));
+        assert_eq!(map.players["a"], Position::new(2, 0));
+        assert_eq!(map.squares[0][2].occupied_by_player.as_deref(), Some("a"));
+        assert_eq!(map.controller_of(Position::new(2, 1)), Team::Blue);
+        assert_eq!(map.quarantine, game.map().quarantine);
+        assert_eq!(loaded.players["a"].team, Team::Blue);
+        assert!(loaded.players["a"].inactive);
+        assert_eq!(loaded.us.as_deref(), Some("a"));
+        assert_eq!(loaded.load_warnings, game.load_warnings);
+    }
+}
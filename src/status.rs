@@ -0,0 +1,157 @@
+use crate::*;
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tiny_http::{Header, Response, Server};
+
+const MAX_RECENT_ERRORS: usize = 20;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorReport {
+    pub time: u64,
+    pub message: String,
+}
+
+/// One of the players we control
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerStatus {
+    pub name: String,
+    /// None if we are not on the map
+    pub position: Option<Position>,
+    pub team: Option<Team>,
+    pub last_decision: Option<Direction>,
+    pub last_reasoning: Vec<String>,
+}
+
+/// What the running bot knows about itself, as served by the status endpoint
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct Status {
+    pub players: Vec<PlayerStatus>,
+    pub player_count: usize,
+    pub squares_by_team: BTreeMap<String, usize>,
+    pub last_server_oid: Option<String>,
+    /// Seconds since the epoch
+    pub last_push_time: Option<u64>,
+    pub recent_errors: VecDeque<ErrorReport>,
+}
+
+pub type SharedStatus = Arc<Mutex<Status>>;
+
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is before the epoch")
+        .as_secs()
+}
+
+impl Status {
+    fn player_mut(&mut self, name: &str) -> &mut PlayerStatus {
+        let index = match self.players.iter().position(|player| player.name == name) {
+            Some(index) => index,
+            None => {
+                self.players.push(PlayerStatus {
+                    name: name.to_owned(),
+                    position: None,
+                    team: None,
+                    last_decision: None,
+                    last_reasoning: Vec::new(),
+                });
+                self.players.len() - 1
+            }
+        };
+        &mut self.players[index]
+    }
+
+    pub fn update_game(&mut self, game: &Game, accounts: &[Account]) {
+        let map = game.map();
+        for account in accounts {
+            let player = self.player_mut(&account.player);
+            player.position = map.players.get(&account.player).copied();
+            player.team = game.players.get(&account.player).map(|player| player.team);
+        }
+        self.player_count = game.current_players().count();
+        self.squares_by_team.clear();
+        for square in map.squares.iter().flatten() {
+            *self
+                .squares_by_team
+                .entry(format!("{:?}", square.controlled_by))
+                .or_insert(0) += 1;
+        }
+    }
+
+    pub fn update_decision(&mut self, name: &str, decision: &ai::Decision) {
+        let player = self.player_mut(name);
+        player.last_decision = Some(decision.direction);
+        player.last_reasoning = decision.reasoning.clone();
+    }
+
+    pub fn report_error(&mut self, message: String) {
+        if self.recent_errors.len() >= MAX_RECENT_ERRORS {
+            self.recent_errors.pop_front();
+        }
+        self.recent_errors.push_back(ErrorReport {
+            time: unix_time(),
+            message,
+        });
+    }
+}
+
+/// The status page includes our reasoning, so it is only ever served on this machine
+fn loopback_addr(addr: &str) -> Result<SocketAddr, String> {
+    let addrs: Vec<SocketAddr> = addr
+        .to_socket_addrs()
+        .map_err(|e| format!("invalid status address {}: {}", addr, e))?
+        .collect();
+    match addrs.first() {
+        Some(first) if addrs.iter().all(|addr| addr.ip().is_loopback()) => Ok(*first),
+        Some(_) => Err(format!(
+            "status address {} is not a loopback address (ex 127.0.0.1:8035)",
+            addr
+        )),
+        None => Err(format!("status address {} resolved to nothing", addr)),
+    }
+}
+
+/// Serves the status as JSON on a background thread. Anything that is not a GET gets a 405.
+pub fn serve(addr: &str, status: SharedStatus) {
+    let addr = loopback_addr(addr).expect("refusing to serve status");
+    let server = Server::http(addr).expect("failed to start status server");
+    info!("Serving status on http://{}", server.server_addr());
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let response = if *request.method() == tiny_http::Method::Get {
+                let body = {
+                    let status = status.lock().expect("status lock poisoned");
+                    serde_json::to_string_pretty(&*status).expect("failed to serialize status")
+                };
+                let content_type =
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                        .expect("invalid header");
+                Response::from_string(body).with_header(content_type)
+            } else {
+                Response::from_string("method not allowed").with_status_code(405)
+            };
+            if let Err(e) = request.respond(response) {
+                warn!("Failed to respond to status request: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_loopback_addresses() {
+        assert!(loopback_addr("127.0.0.1:8035").is_ok());
+        assert!(loopback_addr("[::1]:8035").is_ok());
+        assert!(loopback_addr("0.0.0.0:8035").is_err());
+        assert!(loopback_addr("192.168.1.2:8035").is_err());
+        assert!(loopback_addr("nonsense").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Where players of each team have been over the loaded timeline
+
+use crate::*;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
+pub struct Visits {
+    /// For each team, how many maps in the timeline had one of its players on each square.
+    /// Indexed the same way as Map::squares.
+    pub by_team: BTreeMap<Team, Vec<Vec<u32>>>,
+    /// Number of maps counted
+    pub ticks: usize,
+}
+
+impl Visits {
+    pub fn count(&self, team: Team, pos: Position) -> u32 {
+        if pos.x < 0 || pos.y < 0 {
+            return 0;
+        }
+        self.by_team
+            .get(&team)
+            .and_then(|grid| grid.get(pos.y as usize))
+            .and_then(|row| row.get(pos.x as usize))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Visits by every team other than ours
+    pub fn enemy_count(&self, our_team: Team, pos: Position) -> u32 {
+        self.by_team
+            .keys()
+            .filter(|team| **team != our_team)
+            .map(|team| self.count(*team, pos))
+            .sum()
+    }
+
+    /// One x,y,team,visits line per square a team has visited
+    pub fn to_csv(&self) -> String {
+        let mut csv = "x,y,team,visits\n".to_owned();
+        for (team, grid) in &self.by_team {
+            for (y, row) in grid.iter().enumerate() {
+                for (x, count) in row.iter().enumerate() {
+                    if *count > 0 {
+                        csv += &format!("{},{},{:?},{}\n", x, y, team, count);
+                    }
+                }
+            }
+        }
+        csv
+    }
+}
+
+impl Game {
+    pub fn visits(&self) -> Visits {
+        let latest = self.map();
+        let empty = vec![vec![0; latest.width()]; latest.height()];
+        let mut by_team = BTreeMap::new();
+        for map in &self.timeline {
+            for (name, pos) in &map.players {
+                let team = match self.players.get(name) {
+                    Some(player) if player.team != Team::Null => player.team,
+                    _ => continue,
+                };
+                let grid = by_team.entry(team).or_insert_with(|| empty.clone());
+                // Older maps could be a different size
+                if let Some(count) = grid
+                    .get_mut(pos.y as usize)
+                    .and_then(|row| row.get_mut(pos.x as usize))
+                {
+                    *count += 1;
+                }
+            }
+        }
+        Visits {
+            by_team,
+            ticks: self.timeline.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::fixtures::{game, map};
+
+    #[test]
+    fn visits() {
+        let map = |x: i32| map(&["...", "..."], &[("a", x, 0), ("b", 2, 1)]);
+        let game = game(
+            &[("a", Team::Red), ("b", Team::Blue)],
+            vec![map(0), map(1), map(1)],
+        );
+        let visits = game.visits();
+        assert_eq!(visits.ticks, 3);
+        assert_eq!(visits.count(Team::Red, Position::new(1, 0)), 2);
+        assert_eq!(visits.count(Team::Blue, Position::new(1, 0)), 0);
+        assert_eq!(visits.enemy_count(Team::Red, Position::new(2, 1)), 3);
+        assert_eq!(visits.enemy_count(Team::Blue, Position::new(2, 1)), 0);
+        assert_eq!(
+            visits.to_csv(),
+            "x,y,team,visits\n0,0,Red,1\n1,0,Red,2\n2,1,Blue,3\n"
+        );
+    }
+}
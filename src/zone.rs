@@ -0,0 +1,161 @@
+//! An area of the map a bot is responsible for
+
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+/// A rectangle of squares, from (x, y) to (x + width - 1, y + height - 1)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub fn contains(&self, pos: Position) -> bool {
+        pos.x >= self.x
+            && pos.x < self.x + self.width
+            && pos.y >= self.y
+            && pos.y < self.y + self.height
+    }
+
+    /// Every position in the rectangle, row by row
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let (x, width) = (self.x, self.width);
+        (self.y..self.y + self.height)
+            .flat_map(move |y| (x..x + width).map(move |x| Position::new(x, y)))
+    }
+
+    /// The square in the rectangle closest to pos
+    pub fn clamp(&self, pos: Position) -> Position {
+        Position::new(
+            pos.x.clamp(self.x, self.x + self.width - 1),
+            pos.y.clamp(self.y, self.y + self.height - 1),
+        )
+    }
+}
+
+/// Any number of rectangles. A zone with no rectangles contains nothing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Zone {
+    pub rects: Vec<Rect>,
+}
+
+impl Default for Zone {
+    /// The top left corner
+    fn default() -> Self {
+        Self {
+            rects: vec![Rect {
+                x: 0,
+                y: 0,
+                width: 6,
+                height: 6,
+            }],
+        }
+    }
+}
+
+impl Zone {
+    /// Rectangles with no squares would make closest_to panic, so they are rejected up front
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rects.is_empty() {
+            return Err("zone has no rectangles".to_owned());
+        }
+        for rect in &self.rects {
+            if rect.width <= 0 || rect.height <= 0 {
+                return Err(format!(
+                    "zone rectangle at ({}, {}) is {}x{}, but needs a positive width and height",
+                    rect.x, rect.y, rect.width, rect.height
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, pos: Position) -> bool {
+        self.rects.iter().any(|rect| rect.contains(pos))
+    }
+
+    /// The square in the zone closest to pos (pos itself if it is in the zone)
+    pub fn closest_to(&self, pos: Position) -> Option<Position> {
+        self.rects
+            .iter()
+            .map(|rect| rect.clamp(pos))
+            .min_by_key(|closest| closest.distance(pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn two_rects() -> Zone {
+        Zone {
+            rects: vec![
+                Rect {
+                    x: 0,
+                    y: 0,
+                    width: 3,
+                    height: 3,
+                },
+                Rect {
+                    x: 10,
+                    y: 0,
+                    width: 2,
+                    height: 10,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn validate() {
+        assert!(two_rects().validate().is_ok());
+        assert!(Zone { rects: Vec::new() }.validate().is_err());
+        let mut zone = two_rects();
+        zone.rects[1].width = 0;
+        assert!(zone.validate().is_err());
+    }
+
+    #[test]
+    fn contains() {
+        let zone = two_rects();
+        assert!(zone.contains(Position::new(2, 2)));
+        assert!(!zone.contains(Position::new(3, 2)));
+        assert!(zone.contains(Position::new(11, 9)));
+        assert!(!zone.contains(Position::new(11, 10)));
+    }
+
+    #[test]
+    fn rect_positions() {
+        let rect = two_rects().rects[1];
+        let positions: Vec<Position> = rect.positions().collect();
+        assert_eq!(positions.len(), 20);
+        assert_eq!(positions[1], Position::new(11, 0));
+        assert!(positions.iter().all(|pos| rect.contains(*pos)));
+    }
+
+    #[test]
+    fn closest() {
+        let zone = two_rects();
+        assert_eq!(
+            zone.closest_to(Position::new(4, 4)),
+            Some(Position::new(2, 2))
+        );
+        assert_eq!(
+            zone.closest_to(Position::new(8, 5)),
+            Some(Position::new(10, 5))
+        );
+        assert_eq!(
+            zone.closest_to(Position::new(1, 1)),
+            Some(Position::new(1, 1))
+        );
+        assert_eq!(
+            Zone { rects: Vec::new() }.closest_to(Position::new(1, 1)),
+            None
+        );
+    }
+}